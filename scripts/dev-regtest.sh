@@ -234,6 +234,9 @@ echo "[dev-regtest] starting template-manager (bitcoind backend)..."
   export VELDRA_MANAGER_HTTP_ADDR="${MANAGER_HTTP_ADDR}"
   export VELDRA_VERIFIER_ADDR="${VERIFIER_TCP_ADDR}"
 
+  # Push bitcoind's best block to the verifier for stale-tip checks
+  export VELDRA_VERIFIER_HTTP_URL="http://${VERIFIER_HTTP_ADDR}"
+
   exec "${TEMPLATE_MANAGER_BIN}"
 ) &
 MANAGER_PID=$!
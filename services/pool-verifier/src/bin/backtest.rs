@@ -0,0 +1,233 @@
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+
+use anyhow::{Context, Result, bail};
+use serde::Deserialize;
+
+use pool_verifier::policy::PolicyConfig;
+use pool_verifier::policy_lint::lint_policy_text;
+use pool_verifier::replay::{Replayer, VerdictInput, verdict_log_files};
+
+const DEFAULT_LOG_PATH: &str = "data/verdicts.log";
+const LOG_ROTATIONS: usize = 5;
+const DEFAULT_MAX_FLIPS_SHOWN: usize = 50;
+
+/// The slice of a pool-verifier `LoggedVerdict` line that a replay needs.
+#[derive(Deserialize)]
+struct LoggedLine {
+    log_id: u64,
+    template_id: u64,
+    height: u32,
+    accepted: bool,
+    #[serde(default)]
+    reason_code: Option<String>,
+    #[serde(default)]
+    input: Option<VerdictInput>,
+}
+
+struct Flip {
+    log_id: u64,
+    template_id: u64,
+    height: u32,
+    logged: String,
+    candidate: String,
+}
+
+#[derive(Default)]
+struct Tally {
+    total: u64,
+    accepted: u64,
+    by_reason: BTreeMap<String, u64>,
+}
+
+impl Tally {
+    fn add(&mut self, accepted: bool, code: &str) {
+        self.total += 1;
+        if accepted {
+            self.accepted += 1;
+        }
+        *self.by_reason.entry(code.to_string()).or_insert(0) += 1;
+    }
+
+    fn accept_rate(&self) -> f64 {
+        if self.total == 0 {
+            0.0
+        } else {
+            self.accepted as f64 * 100.0 / self.total as f64
+        }
+    }
+}
+
+fn usage() -> ! {
+    eprintln!("usage: backtest <policy.toml> [--log PATH] [--max-flips N]");
+    eprintln!();
+    eprintln!("Replays PATH (default {DEFAULT_LOG_PATH}) and its .1-.{LOG_ROTATIONS} rotations");
+    eprintln!("through the candidate policy and reports what would change.");
+    std::process::exit(2);
+}
+
+fn load_candidate(path: &str) -> Result<PolicyConfig> {
+    #[derive(Deserialize)]
+    struct Wrapper {
+        policy: PolicyConfig,
+    }
+
+    let text = std::fs::read_to_string(path)
+        .with_context(|| format!("read policy file failed: {}", path))?;
+    let parsed: Wrapper =
+        toml::from_str(&text).with_context(|| format!("policy parse failed for {}", path))?;
+    parsed
+        .policy
+        .validate()
+        .context("policy validation failed")?;
+    for w in lint_policy_text(&text).warnings() {
+        eprintln!("warning: {}", w);
+    }
+    Ok(parsed.policy)
+}
+
+fn main() -> Result<()> {
+    let mut args = std::env::args().skip(1);
+    let mut policy_path: Option<String> = None;
+    let mut log_path = DEFAULT_LOG_PATH.to_string();
+    let mut max_flips = DEFAULT_MAX_FLIPS_SHOWN;
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--log" => log_path = args.next().unwrap_or_else(|| usage()),
+            "--max-flips" => {
+                max_flips = args
+                    .next()
+                    .and_then(|s| s.parse().ok())
+                    .unwrap_or_else(|| usage())
+            }
+            "-h" | "--help" => usage(),
+            _ if policy_path.is_none() => policy_path = Some(arg),
+            _ => usage(),
+        }
+    }
+    let Some(policy_path) = policy_path else {
+        usage()
+    };
+
+    let cfg = load_candidate(&policy_path)?;
+    let files = verdict_log_files(&log_path, LOG_ROTATIONS);
+    if files.is_empty() {
+        bail!("no verdict log found at {} (or its rotations)", log_path);
+    }
+
+    let mut replayer = Replayer::new(cfg);
+    let mut logged = Tally::default();
+    let mut candidate = Tally::default();
+    let mut flips: Vec<Flip> = Vec::new();
+    let mut skipped_no_input = 0u64;
+    let mut unparsable = 0u64;
+
+    for file in &files {
+        let reader = BufReader::new(
+            File::open(file).with_context(|| format!("open {} failed", file.display()))?,
+        );
+        for line in reader.lines().map_while(Result::ok) {
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+            let Ok(entry) = serde_json::from_str::<LoggedLine>(line) else {
+                unparsable += 1;
+                continue;
+            };
+            let Some(ref input) = entry.input else {
+                skipped_no_input += 1;
+                continue;
+            };
+
+            let logged_code = if entry.accepted {
+                "ok".to_string()
+            } else {
+                entry
+                    .reason_code
+                    .clone()
+                    .unwrap_or_else(|| "unknown".to_string())
+            };
+            logged.add(entry.accepted, &logged_code);
+
+            let outcome = replayer.evaluate(input);
+            candidate.add(outcome.accepted(), outcome.primary_code());
+
+            if outcome.accepted() != entry.accepted {
+                flips.push(Flip {
+                    log_id: entry.log_id,
+                    template_id: entry.template_id,
+                    height: entry.height,
+                    logged: logged_code,
+                    candidate: outcome.primary_code().to_string(),
+                });
+            }
+        }
+    }
+
+    println!("Backtest of {} against:", policy_path);
+    for file in &files {
+        println!("  {}", file.display());
+    }
+    println!();
+    println!(
+        "replayed {} verdicts ({} without recorded input skipped, {} unparsable lines)",
+        candidate.total, skipped_no_input, unparsable
+    );
+    if candidate.total == 0 {
+        return Ok(());
+    }
+
+    println!(
+        "accept rate: logged {:.1}% ({}/{}), candidate {:.1}% ({}/{})",
+        logged.accept_rate(),
+        logged.accepted,
+        logged.total,
+        candidate.accept_rate(),
+        candidate.accepted,
+        candidate.total
+    );
+
+    println!();
+    println!(
+        "  {:<32} {:>10} {:>10}",
+        "reason_code", "logged", "candidate"
+    );
+    let mut codes: Vec<&String> = logged
+        .by_reason
+        .keys()
+        .chain(candidate.by_reason.keys())
+        .collect();
+    codes.sort();
+    codes.dedup();
+    for code in codes {
+        println!(
+            "  {:<32} {:>10} {:>10}",
+            code,
+            logged.by_reason.get(code).copied().unwrap_or(0),
+            candidate.by_reason.get(code).copied().unwrap_or(0)
+        );
+    }
+
+    let to_accept = flips.iter().filter(|f| f.candidate == "ok").count();
+    println!();
+    println!(
+        "{} verdicts would flip ({} reject->accept, {} accept->reject)",
+        flips.len(),
+        to_accept,
+        flips.len() - to_accept
+    );
+    for f in flips.iter().take(max_flips) {
+        println!(
+            "  log_id={} template_id={} height={} {} -> {}",
+            f.log_id, f.template_id, f.height, f.logged, f.candidate
+        );
+    }
+    if flips.len() > max_flips {
+        println!("  ... {} more (raise --max-flips)", flips.len() - max_flips);
+    }
+
+    Ok(())
+}
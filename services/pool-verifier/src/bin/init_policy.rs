@@ -39,6 +39,18 @@ fn read_u32_with_default(prompt: &str, default: u32) -> io::Result<u32> {
     }
 }
 
+fn read_f64_with_default(prompt: &str, default: f64) -> io::Result<f64> {
+    let full = format!("{prompt} [{default}]");
+    let s = read_line(&full)?;
+    if s.is_empty() {
+        Ok(default)
+    } else {
+        s.parse::<f64>().map_err(|e| {
+            io::Error::new(io::ErrorKind::InvalidInput, format!("invalid number: {e}"))
+        })
+    }
+}
+
 fn read_bool_with_default(prompt: &str, default: bool) -> io::Result<bool> {
     let full = format!("{prompt} [{}]", if default { "Y" } else { "N" });
     let s = read_line(&full)?.to_ascii_lowercase();
@@ -95,6 +107,11 @@ fn main() -> Result<()> {
     let min_avg_fee_mid = read_u64_with_default("Mid tier floor", 1_000)?;
     let min_avg_fee_hi = read_u64_with_default("High tier floor", 5_000)?;
 
+    println!("\nMinimum template feerate (sat/vB) for each tier, 0 disables");
+    let min_feerate_lo = read_f64_with_default("Low tier feerate floor", 0.0)?;
+    let min_feerate_mid = read_f64_with_default("Mid tier feerate floor", 0.0)?;
+    let min_feerate_hi = read_f64_with_default("High tier feerate floor", 0.0)?;
+
     let unknown_mempool_as_high = read_bool_with_default(
         "If mempool backend is unavailable, treat tier as high",
         true,
@@ -115,6 +132,9 @@ fn main() -> Result<()> {
     cfg.min_avg_fee_lo = min_avg_fee_lo;
     cfg.min_avg_fee_mid = min_avg_fee_mid;
     cfg.min_avg_fee_hi = min_avg_fee_hi;
+    cfg.min_feerate_lo = min_feerate_lo;
+    cfg.min_feerate_mid = min_feerate_mid;
+    cfg.min_feerate_hi = min_feerate_hi;
 
     cfg.unknown_mempool_as_high = unknown_mempool_as_high;
     cfg.reject_coinbase_zero = reject_coinbase_zero;
@@ -0,0 +1,20 @@
+use serde::{Deserialize, Serialize};
+
+/// Best block as reported by a chain-tip source (bitcoind RPC or a manager push).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChainTip {
+    /// Block hash, 64 hex chars, same byte order as `TemplatePropose::prev_hash`.
+    pub hash: String,
+    pub height: u32,
+
+    /// When the verifier learned about this tip. Set by the verifier, not the sender.
+    #[serde(default)]
+    pub received_at_unix_ms: u64,
+}
+
+impl ChainTip {
+    /// True when `prev_hash` names this block (hex compare, case-insensitive).
+    pub fn is_parent_of(&self, prev_hash: &str) -> bool {
+        self.hash.eq_ignore_ascii_case(prev_hash)
+    }
+}
@@ -0,0 +1,173 @@
+use pool_verifier::chain_tip::ChainTip;
+use serde::Deserialize;
+use serde_json::json;
+use std::sync::{Arc, OnceLock, RwLock};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+static CLIENT: OnceLock<reqwest::Client> = OnceLock::new();
+
+fn client() -> &'static reqwest::Client {
+    CLIENT.get_or_init(|| {
+        reqwest::Client::builder()
+            .timeout(Duration::from_millis(900))
+            .build()
+            .expect("reqwest client")
+    })
+}
+
+/// Where the verifier learns the current best block.
+#[derive(Debug, Clone)]
+pub enum ChainTipSource {
+    /// Poll bitcoind `getblockchaininfo` in the background (`run_tip_poller`).
+    Rpc {
+        url: String,
+        user: Option<String>,
+        pass: Option<String>,
+    },
+    /// Only tips pushed to `POST /chain/tip` (e.g. by template-manager).
+    Pushed,
+}
+
+impl ChainTipSource {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ChainTipSource::Rpc { .. } => "bitcoind_rpc",
+            ChainTipSource::Pushed => "pushed",
+        }
+    }
+}
+
+pub fn chain_tip_source_from_env() -> ChainTipSource {
+    let non_empty = |k: &str| std::env::var(k).ok().filter(|s| !s.trim().is_empty());
+
+    match non_empty("VELDRA_CHAIN_TIP_RPC_URL") {
+        Some(url) => ChainTipSource::Rpc {
+            url,
+            user: non_empty("VELDRA_CHAIN_TIP_RPC_USER"),
+            pass: non_empty("VELDRA_CHAIN_TIP_RPC_PASS"),
+        },
+        None => ChainTipSource::Pushed,
+    }
+}
+
+/// Tips older than this are treated as unknown, so a dead source cannot
+/// turn every template into a stale-tip reject.
+pub fn chain_tip_max_age_ms_from_env() -> u64 {
+    std::env::var("VELDRA_CHAIN_TIP_MAX_AGE_SECS")
+        .ok()
+        .and_then(|s| s.parse::<u64>().ok())
+        .unwrap_or(120)
+        .saturating_mul(1000)
+}
+
+/// How often `ChainTipSource::Rpc` polls bitcoind. A new block counts as the
+/// tip at most this long after bitcoind sees it.
+pub fn chain_tip_poll_interval_from_env() -> Duration {
+    let ms = std::env::var("VELDRA_CHAIN_TIP_POLL_MS")
+        .ok()
+        .and_then(|s| s.parse::<u64>().ok())
+        .filter(|ms| *ms > 0)
+        .unwrap_or(500);
+    Duration::from_millis(ms)
+}
+
+fn now_unix_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_else(|_| Duration::from_secs(0))
+        .as_millis() as u64
+}
+
+#[derive(Deserialize)]
+struct RpcResponse {
+    result: Option<BlockchainInfo>,
+    #[serde(default)]
+    error: Option<serde_json::Value>,
+}
+
+#[derive(Deserialize)]
+struct BlockchainInfo {
+    blocks: u32,
+    bestblockhash: String,
+}
+
+pub async fn fetch_tip_rpc(
+    url: &str,
+    user: Option<&str>,
+    pass: Option<&str>,
+) -> Result<ChainTip, String> {
+    let body = json!({
+        "jsonrpc": "1.0",
+        "id": "veldra",
+        "method": "getblockchaininfo",
+        "params": [],
+    });
+
+    let mut req = client().post(url).json(&body);
+    if let Some(user) = user {
+        req = req.basic_auth(user, pass);
+    }
+
+    let resp = req
+        .send()
+        .await
+        .map_err(|e| format!("HTTP error fetching {}: {e:?}", url))?;
+
+    let status = resp.status();
+    if !status.is_success() {
+        return Err(format!("non-success status {} from {}", status, url));
+    }
+
+    let parsed = resp
+        .json::<RpcResponse>()
+        .await
+        .map_err(|e| format!("JSON parse error from {}: {e:?}", url))?;
+
+    match parsed.result {
+        Some(info) => Ok(ChainTip {
+            hash: info.bestblockhash,
+            height: info.blocks,
+            received_at_unix_ms: now_unix_ms(),
+        }),
+        None => Err(format!("RPC error from {}: {:?}", url, parsed.error)),
+    }
+}
+
+/// Keep `chain_tip` current from bitcoind, so templates never wait on RPC.
+/// Failed polls leave the last tip in place; it goes stale via `fresh_tip`.
+pub async fn run_tip_poller(
+    chain_tip: Arc<RwLock<Option<ChainTip>>>,
+    url: String,
+    user: Option<String>,
+    pass: Option<String>,
+    interval: Duration,
+) {
+    let mut ticker = tokio::time::interval(interval);
+    ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+    // Log when polling starts or stops failing, not on every tick.
+    let mut failing = false;
+    loop {
+        ticker.tick().await;
+        match fetch_tip_rpc(&url, user.as_deref(), pass.as_deref()).await {
+            Ok(tip) => {
+                if failing {
+                    println!("[chain_tip] polling {} recovered", url);
+                    failing = false;
+                }
+                *chain_tip.write().unwrap() = Some(tip);
+            }
+            Err(e) => {
+                if !failing {
+                    eprintln!("[chain_tip] {e}; retrying every {:?}", interval);
+                    failing = true;
+                }
+            }
+        }
+    }
+}
+
+/// `tip` if it is recent enough to judge templates against.
+pub fn fresh_tip(tip: Option<ChainTip>, max_age_ms: u64) -> Option<ChainTip> {
+    tip.filter(|t| now_unix_ms().saturating_sub(t.received_at_unix_ms) <= max_age_ms)
+}
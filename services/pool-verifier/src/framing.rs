@@ -0,0 +1,51 @@
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+
+use rg_protocol::{
+    DEFAULT_MAX_LINE_BYTES, ProtocolError, ProtocolErrorCode, TemplatePropose, decode_message,
+};
+
+/// Longest accepted protocol line (`VELDRA_MAX_LINE_BYTES`), newline excluded.
+pub fn max_line_bytes_from_env() -> usize {
+    std::env::var("VELDRA_MAX_LINE_BYTES")
+        .ok()
+        .and_then(|s| s.parse::<usize>().ok())
+        .filter(|n| *n > 0)
+        .unwrap_or(DEFAULT_MAX_LINE_BYTES)
+}
+
+/// `VELDRA_PROTOCOL_STRICT=1` rejects proposals with fields this build does not know.
+pub fn protocol_strict_from_env() -> bool {
+    std::env::var("VELDRA_PROTOCOL_STRICT")
+        .map(|v| matches!(v.trim(), "1" | "true" | "yes"))
+        .unwrap_or(false)
+}
+
+/// Read a frame as a `TemplatePropose`. In strict mode, keys the struct does
+/// not define are an error instead of being ignored.
+pub fn parse_propose(
+    frame: &serde_json::Value,
+    strict: bool,
+) -> Result<TemplatePropose, ProtocolError> {
+    decode_message(frame, strict)
+}
+
+static PARSE_ERRORS: Mutex<BTreeMap<&'static str, u64>> = Mutex::new(BTreeMap::new());
+
+pub fn record_parse_error(code: ProtocolErrorCode) {
+    *PARSE_ERRORS
+        .lock()
+        .unwrap()
+        .entry(code.as_str())
+        .or_default() += 1;
+}
+
+/// Lines rejected since startup, by error code.
+pub fn parse_error_counts() -> BTreeMap<String, u64> {
+    PARSE_ERRORS
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|(k, v)| (k.to_string(), *v))
+        .collect()
+}
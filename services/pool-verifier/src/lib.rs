@@ -1 +1,9 @@
+pub mod chain_tip;
+pub mod mempool;
 pub mod policy;
+pub mod policy_lint;
+pub mod replay;
+pub mod rules;
+pub mod schedule;
+pub mod script_rules;
+pub mod tier_state;
@@ -6,30 +6,45 @@ use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::{Arc, Mutex, OnceLock, RwLock};
 use std::time::{SystemTime, UNIX_EPOCH};
 
-use crate::state::AppState;
+use crate::state::{AppState, CommitError, PolicySource, ShadowPolicy};
 
 use axum::{
     Json, Router,
     body::Bytes,
-    extract::{Extension, Query, State},
+    extract::{Extension, Path, Query, State},
     http::StatusCode,
     response::{Html, IntoResponse},
     routing::{get, post},
 };
 
+use futures_util::{SinkExt, StreamExt};
 use serde::{Deserialize, Serialize};
 use serde_json::json;
-use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
-use tokio::net::TcpListener;
+use tokio::net::{TcpListener, TcpStream};
 use tokio::time::{Duration, timeout};
+use tokio_util::codec::Framed;
 
-use pool_verifier::policy::{PolicyConfig, VerdictReason as LocalReason};
+use pool_verifier::chain_tip::ChainTip;
+use pool_verifier::mempool::MempoolSnapshot;
+use pool_verifier::policy::{
+    EvalContext, FeeTierConfig, PolicyConfig, TierMetric, ValidationReport,
+    VerdictReason as LocalReason, template_feerate_sat_vb,
+};
+use pool_verifier::policy_lint::{PolicyLint, lint_policy_text};
+use pool_verifier::replay::VerdictInput;
+use pool_verifier::tier_state::TierState;
 use rg_protocol::{
-    PROTOCOL_VERSION, PolicyContext, TemplatePropose, TemplateVerdict, VerdictReason as WireReason,
+    Capability, ErrorFrame, HandshakeMessage, HelloAck, MessageCodec, Negotiated, PROTOCOL_VERSION,
+    PolicyContext, ProtocolError, TemplatePropose, TemplateVerdict, VerdictReason as WireReason,
+    Violation, WireEncoding,
 };
 
+mod chain_tip_client;
+mod framing;
 mod mempool_client;
+mod policy_watch;
 mod state;
+use chain_tip_client::{ChainTipSource, chain_tip_max_age_ms_from_env, chain_tip_source_from_env};
 use mempool_client::mempool_url_from_env;
 
 #[derive(Deserialize)]
@@ -65,6 +80,52 @@ struct LoggedVerdict {
     pub min_avg_fee_used: u64,
     pub fee_tier: String, // "low" | "mid" | "high"
     pub avg_fee_sats_per_tx: u64,
+
+    #[serde(default)]
+    pub observed_weight: Option<u64>,
+
+    #[serde(default)]
+    pub feerate_sat_vb: Option<f64>,
+    #[serde(default)]
+    pub min_feerate_used: f64,
+
+    /// Every failed rule code, primary first. Empty for accepts and old log lines.
+    #[serde(default)]
+    pub violation_codes: Vec<String>,
+
+    #[serde(default)]
+    pub coinbase_value: u64,
+    /// Sender replaced a zero coinbase_value with its own fallback.
+    #[serde(default)]
+    pub coinbase_value_substituted: bool,
+
+    #[serde(default)]
+    pub created_at_unix_ms: Option<u64>,
+    /// Sender's created_at_unix_ms to verdict, in ms. None when the sender omits it.
+    #[serde(default)]
+    pub latency_ms: Option<u64>,
+
+    /// Shadow policy outcome, when one was set. Never affects `accepted`.
+    #[serde(default)]
+    pub shadow_accepted: Option<bool>,
+    #[serde(default)]
+    pub shadow_reason_code: Option<String>,
+
+    /// Full evaluation input, for `backtest`. Absent on lines written before it existed.
+    #[serde(default)]
+    pub input: Option<VerdictInput>,
+
+    /// Active policy version at verdict time; `/policy/history/{version}` has its TOML.
+    #[serde(default)]
+    pub policy_version: Option<u64>,
+
+    /// Rules in `warn` mode that failed. They never affect `accepted`.
+    #[serde(default)]
+    pub warning_codes: Vec<String>,
+
+    /// Scheduled overlay in effect at verdict time.
+    #[serde(default)]
+    pub overlay: Option<String>,
 }
 
 #[derive(Serialize)]
@@ -72,18 +133,31 @@ struct StatsResponse {
     total: u64,
     accepted: u64,
     rejected: u64,
+    /// Primary outcome per verdict ("ok" or the reject code), plus a
+    /// "warn:<code>" entry for each advisory rule failure.
     by_reason: BTreeMap<String, u64>,
+    /// Counts every violation, primary and secondary.
+    by_violation: BTreeMap<String, u64>,
     by_tier: BTreeMap<String, u64>,
     last: Option<LoggedVerdict>,
+    current_tier: Option<String>,
+    tier_changed_at_unix_ms: Option<u64>,
+    /// Protocol lines rejected since startup, by error code.
+    parse_errors: BTreeMap<String, u64>,
 }
 
 #[derive(Deserialize)]
 struct ApplyPolicyReq {
+    tiers: Option<Vec<FeeTierConfig>>,
+    tier_metric: Option<TierMetric>,
     low_mempool_tx: Option<u64>,
     high_mempool_tx: Option<u64>,
     min_avg_fee_lo: Option<u64>,
     min_avg_fee_mid: Option<u64>,
     min_avg_fee_hi: Option<u64>,
+    min_feerate_lo: Option<f64>,
+    min_feerate_mid: Option<f64>,
+    min_feerate_hi: Option<f64>,
     min_total_fees: Option<u64>,
     max_tx_count: Option<u32>,
 }
@@ -203,10 +277,18 @@ async fn main() -> anyhow::Result<()> {
 
     std::fs::create_dir_all("data")?;
 
-    let policy_holder = crate::state::safe_initial_policy(&policy_path);
+    let policy_strict = crate::state::policy_strict_from_env();
+    let mut policy_holder = crate::state::safe_initial_policy(&policy_path, policy_strict);
+    policy_holder.resume_history(crate::state::POLICY_HISTORY_PATH);
+    println!("[policy] active version {}", policy_holder.version);
 
     let app_state = AppState {
         policy: Arc::new(RwLock::new(policy_holder)),
+        tier_state: Arc::new(Mutex::new(TierState::default())),
+        chain_tip: Arc::new(RwLock::new(None)),
+        shadow: Arc::new(Mutex::new(None)),
+        policy_path: policy_path.clone(),
+        policy_strict,
     };
 
     let (verdict_log, log_id_counter) = load_verdict_log();
@@ -226,6 +308,20 @@ async fn main() -> anyhow::Result<()> {
     let mempool_url = mempool_url_from_env();
     let tcp_mempool_url = mempool_url.clone();
 
+    let tip_source = chain_tip_source_from_env();
+    println!("Chain tip source: {}", tip_source.as_str());
+    if let ChainTipSource::Rpc { url, user, pass } = tip_source {
+        let interval = chain_tip_client::chain_tip_poll_interval_from_env();
+        println!("Chain tip poll: every {}ms", interval.as_millis());
+        tokio::spawn(chain_tip_client::run_tip_poller(
+            app_state.chain_tip.clone(),
+            url,
+            user,
+            pass,
+            interval,
+        ));
+    }
+
     let tcp_task = tokio::spawn(async move {
         if let Err(e) = run_tcp_server(
             tcp_state,
@@ -240,6 +336,16 @@ async fn main() -> anyhow::Result<()> {
         }
     });
 
+    let watch_interval = policy_watch::policy_watch_interval_from_env();
+    match watch_interval {
+        Some(d) => println!("Policy hot reload: polling every {}s, SIGHUP", d.as_secs()),
+        None => println!("Policy hot reload: SIGHUP only"),
+    }
+    tokio::spawn(policy_watch::run_policy_watcher(
+        app_state.clone(),
+        watch_interval,
+    ));
+
     let http_task = tokio::spawn(async move {
         if let Err(e) = run_http_server(http_addr, http_log, http_ui_mode, http_state).await {
             eprintln!("http server error: {e:?}");
@@ -250,50 +356,152 @@ async fn main() -> anyhow::Result<()> {
     Ok(())
 }
 
-fn map_local_to_wire(
-    reason: &LocalReason,
+fn build_policy_context(
     fee_tier: &str,
     min_avg_fee_used: u64,
+    feerate_sat_vb: Option<f64>,
+    min_feerate_used: f64,
+    tier_changed_at_unix_ms: Option<u64>,
+    tier_metric_value: Option<f64>,
     cfg: &PolicyConfig,
-) -> (Option<WireReason>, Option<String>, Option<PolicyContext>) {
-    if matches!(reason, LocalReason::Ok) {
-        return (
-            None,
-            None,
-            Some(PolicyContext {
-                fee_tier: Some(fee_tier.to_string()),
-                min_avg_fee_used: Some(min_avg_fee_used),
-                min_total_fees_used: Some(cfg.min_total_fees),
-                reject_coinbase_zero: Some(cfg.reject_coinbase_zero),
-                unknown_mempool_as_high: Some(cfg.unknown_mempool_as_high),
-            }),
-        );
+) -> PolicyContext {
+    PolicyContext {
+        fee_tier: Some(fee_tier.to_string()),
+        min_avg_fee_used: Some(min_avg_fee_used),
+        min_total_fees_used: Some(cfg.min_total_fees),
+        reject_coinbase_zero: Some(cfg.reject_coinbase_zero),
+        unknown_mempool_as_high: Some(cfg.unknown_mempool_as_high),
+        max_weight_used: Some(cfg.max_block_weight()),
+        feerate_sat_vb,
+        min_feerate_used: Some(min_feerate_used),
+        tier_changed_at_unix_ms,
+        tier_metric: Some(cfg.tier_metric.as_str().to_string()),
+        tier_metric_value,
+        chain_tip_hash: None,
+        chain_tip_height: None,
+        policy_version: None,
+        overlay: None,
     }
+}
 
-    let (code, detail) = match reason {
+fn map_local_to_wire(reason: &LocalReason) -> Option<Violation> {
+    let (code, detail, threshold, observed) = match reason {
+        LocalReason::Ok => return None,
         LocalReason::ProtocolVersionMismatch { got, expected } => (
             WireReason::ProtocolVersionMismatch,
             format!("protocol_version got={} expected={}", got, expected),
+            Some(*expected as f64),
+            Some(*got as f64),
         ),
         LocalReason::PrevHashLenMismatch { len, expected } => (
             WireReason::PrevHashLenMismatch,
             format!("prev_hash len={} expected={}", len, expected),
+            Some(*expected as f64),
+            Some(*len as f64),
         ),
         LocalReason::InvalidPrevHash => (
             WireReason::InvalidPrevHash,
             "prev_hash contains non-hex characters".to_string(),
+            None,
+            None,
+        ),
+        LocalReason::StalePrevHash { got, expected } => (
+            WireReason::StalePrevHash,
+            format!("prev_hash={} is not chain tip={}", got, expected),
+            None,
+            None,
+        ),
+        LocalReason::HeightMismatch { height, expected } => (
+            WireReason::HeightMismatch,
+            format!("block_height={} expected={} (tip+1)", height, expected),
+            Some(*expected as f64),
+            Some(*height as f64),
+        ),
+        LocalReason::TemplateTooOld {
+            age_ms,
+            max_allowed,
+        } => (
+            WireReason::TemplateTooOld,
+            format!(
+                "template age={}ms > max_template_age_ms={}",
+                age_ms, max_allowed
+            ),
+            Some(*max_allowed as f64),
+            Some(*age_ms as f64),
+        ),
+        LocalReason::TemplateTimestampInFuture { ahead_ms, max_skew } => (
+            WireReason::TemplateTimestampInFuture,
+            format!(
+                "created_at is {}ms in the future > max_clock_skew_ms={}",
+                ahead_ms, max_skew
+            ),
+            Some(*max_skew as f64),
+            Some(*ahead_ms as f64),
         ),
         LocalReason::EmptyTemplateRejected => (
             WireReason::EmptyTemplateRejected,
             "empty template rejected by policy".to_string(),
+            None,
+            Some(0.0),
         ),
-        LocalReason::CoinbaseValueZeroRejected => (
+        LocalReason::CoinbaseValueZeroRejected { substituted } => (
             WireReason::CoinbaseValueZeroRejected,
-            "coinbase_value=0 rejected by policy".to_string(),
+            if *substituted {
+                "coinbase_value=0 from backend (sender substituted a fallback) rejected by policy"
+                    .to_string()
+            } else {
+                "coinbase_value=0 rejected by policy".to_string()
+            },
+            None,
+            Some(0.0),
+        ),
+        LocalReason::CoinbaseValueOverclaim {
+            coinbase_value,
+            expected,
+        } => (
+            WireReason::CoinbaseValueOverclaim,
+            format!(
+                "coinbase_value={} > subsidy+fees={}",
+                coinbase_value, expected
+            ),
+            Some(*expected as f64),
+            Some(*coinbase_value as f64),
+        ),
+        LocalReason::CoinbaseValueUnderclaim {
+            coinbase_value,
+            expected,
+            tolerance,
+            substituted,
+        } => (
+            WireReason::CoinbaseValueUnderclaim,
+            format!(
+                "coinbase_value={}{} < subsidy+fees={} by more than tolerance={}",
+                coinbase_value,
+                if *substituted {
+                    " from backend (sender substituted a fallback)"
+                } else {
+                    ""
+                },
+                expected,
+                tolerance
+            ),
+            Some(expected.saturating_sub(*tolerance) as f64),
+            Some(*coinbase_value as f64),
         ),
         LocalReason::TxCountExceeded { count, max_allowed } => (
             WireReason::TxCountExceeded,
             format!("tx_count={} > max_tx_count={}", count, max_allowed),
+            Some(*max_allowed as f64),
+            Some(*count as f64),
+        ),
+        LocalReason::WeightLimitExceeded {
+            weight,
+            max_allowed,
+        } => (
+            WireReason::WeightLimitExceeded,
+            format!("weight={} > max_weight={}", weight, max_allowed),
+            Some(*max_allowed as f64),
+            Some(*weight as f64),
         ),
         LocalReason::TotalFeesBelowMinimum {
             total,
@@ -301,40 +509,105 @@ fn map_local_to_wire(
         } => (
             WireReason::TotalFeesBelowMinimum,
             format!("total_fees={} < min_total_fees={}", total, min_required),
+            Some(*min_required as f64),
+            Some(*total as f64),
         ),
         LocalReason::AvgFeeBelowMinimum { avg, min_required } => (
             WireReason::AvgFeeBelowMinimum,
             format!("avg_fee={} < min_avg_fee_used={}", avg, min_required),
+            Some(*min_required as f64),
+            Some(*avg as f64),
+        ),
+        LocalReason::FeerateBelowMinimum {
+            feerate,
+            min_required,
+        } => (
+            WireReason::FeerateBelowMinimum,
+            format!(
+                "feerate={:.2} sat/vB < min_feerate_used={:.2} sat/vB",
+                feerate, min_required
+            ),
+            Some(*min_required),
+            Some(*feerate),
+        ),
+        LocalReason::Custom {
+            code,
+            detail,
+            threshold,
+            observed,
+        } => (
+            WireReason::Custom(code.clone()),
+            detail.clone(),
+            *threshold,
+            *observed,
         ),
-        LocalReason::Ok => unreachable!(),
     };
 
-    let ctx = PolicyContext {
-        fee_tier: Some(fee_tier.to_string()),
-        min_avg_fee_used: Some(min_avg_fee_used),
-        min_total_fees_used: Some(cfg.min_total_fees),
-        reject_coinbase_zero: Some(cfg.reject_coinbase_zero),
-        unknown_mempool_as_high: Some(cfg.unknown_mempool_as_high),
-    };
+    Some(Violation {
+        reason_code: code,
+        reason_detail: Some(detail),
+        threshold,
+        observed,
+    })
+}
 
-    (Some(code), Some(detail), Some(ctx))
+/// Run the shadow policy on a proposal and count (dis)agreement with the active verdict.
+/// Returns the shadow's (accepted, primary reason code).
+fn evaluate_shadow(
+    shadow: &mut ShadowPolicy,
+    propose: &TemplatePropose,
+    mempool: Option<&MempoolSnapshot>,
+    eval_ctx: EvalContext<'_>,
+    now_ms: u64,
+    active_code: &str,
+) -> (bool, String) {
+    let (cfg, _) = shadow
+        .config
+        .clone()
+        .with_active_overlay(propose.block_height, now_ms);
+    let tier = shadow.tier_state.select(&cfg, mempool, now_ms);
+    let (reasons, _, _, _) = shadow.rules.evaluate(propose, &cfg, tier, eval_ctx);
+
+    let shadow_code = reasons
+        .iter()
+        .find_map(map_local_to_wire)
+        .map(|v| wire_reason_code_str(&v.reason_code).to_string())
+        .unwrap_or_else(|| "ok".to_string());
+
+    if !shadow.record(active_code, &shadow_code) {
+        println!(
+            "[shadow] disagree template id={} height={} active={} shadow={}",
+            propose.id, propose.block_height, active_code, shadow_code
+        );
+    }
+
+    (reasons.is_empty(), shadow_code)
 }
 
-fn wire_reason_code_str(r: &WireReason) -> &'static str {
+fn wire_reason_code_str(r: &WireReason) -> &str {
     match r {
         WireReason::ProtocolVersionMismatch => "protocol_version_mismatch",
         WireReason::PrevHashLenMismatch => "prev_hash_len_mismatch",
         WireReason::InvalidPrevHash => "invalid_prev_hash",
+        WireReason::StalePrevHash => "stale_prev_hash",
+        WireReason::HeightMismatch => "height_mismatch",
+        WireReason::TemplateTooOld => "template_too_old",
+        WireReason::TemplateTimestampInFuture => "template_timestamp_in_future",
         WireReason::EmptyTemplateRejected => "empty_template_rejected",
         WireReason::CoinbaseValueZeroRejected => "coinbase_value_zero_rejected",
+        WireReason::CoinbaseValueOverclaim => "coinbase_value_overclaim",
+        WireReason::CoinbaseValueUnderclaim => "coinbase_value_underclaim",
         WireReason::TxCountExceeded => "tx_count_exceeded",
+        WireReason::WeightLimitExceeded => "weight_limit_exceeded",
         WireReason::TotalFeesBelowMinimum => "total_fees_below_minimum",
         WireReason::AvgFeeBelowMinimum => "avg_fee_below_minimum",
+        WireReason::FeerateBelowMinimum => "feerate_below_minimum",
 
         // New rg-protocol variants (must be covered)
         WireReason::PolicyLoadError => "policy_load_error",
         WireReason::MempoolBackendUnavailable => "mempool_backend_unavailable",
         WireReason::InternalError => "internal_error",
+        WireReason::Custom(code) => code,
     }
 }
 
@@ -364,6 +637,30 @@ fn normalize_reason_key(s: &str) -> String {
     }
 }
 
+/// Features this verifier offers in the connect handshake.
+const VERIFIER_CAPABILITIES: &[Capability] = &[
+    Capability::Violations,
+    Capability::Warnings,
+    Capability::PolicyContext,
+    Capability::Feerate,
+    Capability::Cbor,
+];
+
+fn verifier_agent() -> String {
+    format!("pool-verifier/{}", env!("CARGO_PKG_VERSION"))
+}
+
+/// Count a rejected line and tell the sender why, instead of leaving it to time out.
+async fn reply_protocol_error(
+    framed: &mut Framed<TcpStream, MessageCodec>,
+    peer: std::net::SocketAddr,
+    error: ProtocolError,
+) -> std::io::Result<()> {
+    framing::record_parse_error(error.code);
+    eprintln!("[protocol] peer {}: {}", peer, error);
+    framed.send(ErrorFrame { error }).await
+}
+
 async fn run_tcp_server(
     app_state: AppState,
     addr: String,
@@ -374,61 +671,152 @@ async fn run_tcp_server(
     let listener = TcpListener::bind(&addr).await?;
     println!("TCP listening on {}", addr);
 
+    let tip_max_age_ms = chain_tip_max_age_ms_from_env();
+    let max_line_bytes = framing::max_line_bytes_from_env();
+    let protocol_strict = framing::protocol_strict_from_env();
+
     loop {
-        let (stream, _peer) = listener.accept().await?;
+        let (stream, peer) = listener.accept().await?;
         let state_clone = app_state.clone();
         let log = verdict_log.clone();
         let url_clone = mempool_url.clone();
         let id_ctr = log_id_counter.clone();
 
         tokio::spawn(async move {
-            let (reader, mut writer) = stream.into_split();
-            let mut reader = BufReader::new(reader);
-            let mut line = String::new();
-
-            loop {
-                line.clear();
-                let _n = match reader.read_line(&mut line).await {
-                    Ok(0) => break,
-                    Ok(n) => n,
+            let mut framed = Framed::new(stream, MessageCodec::new(max_line_bytes));
+            // Settled by a Hello on the first line; peers that skip it get the legacy set.
+            let mut negotiated: Option<Negotiated> = None;
+
+            while let Some(next) = framed.next().await {
+                let frame = match next {
+                    Ok(Ok(frame)) => frame,
+                    Ok(Err(err)) => {
+                        if reply_protocol_error(&mut framed, peer, err).await.is_err() {
+                            break;
+                        }
+                        continue;
+                    }
                     Err(e) => {
                         eprintln!("read error: {e:?}");
                         break;
                     }
                 };
 
-                let propose: TemplatePropose = match serde_json::from_str(&line) {
+                if negotiated.is_none() {
+                    if let Ok(HandshakeMessage::Hello(hello)) =
+                        HandshakeMessage::deserialize(&frame)
+                    {
+                        let (ack, result) = match hello.negotiate(VERIFIER_CAPABILITIES, &[]) {
+                            Ok(n) => (HelloAck::accepted(verifier_agent(), &n), Ok(n)),
+                            Err(e) => (HelloAck::rejected(verifier_agent(), e.clone()), Err(e)),
+                        };
+                        let sent = framed.send(HandshakeMessage::HelloAck(ack)).await;
+                        match result {
+                            Ok(n) if sent.is_ok() => {
+                                let encoding = WireEncoding::negotiated(&n);
+                                framed.codec_mut().set_encoding(encoding);
+                                println!(
+                                    "[handshake] peer {} agent={} version={} capabilities={:?} encoding={}",
+                                    peer,
+                                    hello.agent,
+                                    n.version,
+                                    n.capabilities,
+                                    encoding.as_str()
+                                );
+                                negotiated = Some(n);
+                                continue;
+                            }
+                            Ok(_) => break,
+                            Err(e) => {
+                                eprintln!(
+                                    "[handshake] rejected peer {} agent={}: {}",
+                                    peer, hello.agent, e
+                                );
+                                break;
+                            }
+                        }
+                    }
+                    negotiated = Some(Negotiated::legacy(VERIFIER_CAPABILITIES));
+                }
+                let caps = negotiated
+                    .as_ref()
+                    .map(|n| n.capabilities.as_slice())
+                    .unwrap_or_default();
+
+                let propose = match framing::parse_propose(&frame, protocol_strict) {
                     Ok(p) => p,
-                    Err(e) => {
-                        eprintln!("parse error: {e:?}");
+                    Err(err) => {
+                        if reply_protocol_error(&mut framed, peer, err).await.is_err() {
+                            break;
+                        }
                         continue;
                     }
                 };
+                // Age is judged on arrival, before mempool / tip lookups add their own delay.
+                let received_at_ms = current_timestamp_ms();
 
-                let mempool_tx_count: Option<u64> = if let Some(ref url) = url_clone {
+                let mempool: Option<MempoolSnapshot> = if let Some(ref url) = url_clone {
                     timeout(
                         Duration::from_millis(600),
-                        mempool_client::fetch_mempool_tx_count(url),
+                        mempool_client::fetch_mempool_snapshot(url),
                     )
                     .await
-                    .ok() // Result<Option<u64>, Elapsed> -> Option<Option<u64>>
-                    .flatten() // Option<Option<u64>> -> Option<u64>
+                    .ok() // Result<Option<_>, Elapsed> -> Option<Option<_>>
+                    .flatten() // Option<Option<_>> -> Option<_>
                 } else {
                     None
                 };
 
-                let cfg = {
+                let (cfg, rules, policy_version) = {
                     let holder = state_clone.policy.read().unwrap();
-                    holder.config.clone()
+                    (holder.config.clone(), holder.rules.clone(), holder.version)
                 };
 
-                let (reason_enum, fee_tier, min_avg_fee_used) =
-                    pool_verifier::policy::evaluate_dynamic(&propose, &cfg, mempool_tx_count);
+                let evaluated_at_ms = current_timestamp_ms();
+                let (cfg, overlay) = cfg.with_active_overlay(propose.block_height, evaluated_at_ms);
+                let (selected_tier, tier_changed_at_unix_ms) = {
+                    let mut tiers = state_clone.tier_state.lock().unwrap();
+                    let t = tiers.select(&cfg, mempool.as_ref(), evaluated_at_ms);
+                    (t, tiers.changed_at_unix_ms)
+                };
+
+                let tip: Option<ChainTip> = chain_tip_client::fresh_tip(
+                    state_clone.chain_tip.read().unwrap().clone(),
+                    tip_max_age_ms,
+                );
+
+                let eval_ctx = EvalContext {
+                    chain_tip: tip.as_ref(),
+                    received_at_unix_ms: Some(received_at_ms),
+                    mempool: mempool.as_ref(),
+                };
+
+                let (reasons, warning_reasons, fee_tier, min_avg_fee_used) =
+                    rules.evaluate(&propose, &cfg, selected_tier, eval_ctx);
 
-                let accepted = matches!(reason_enum, LocalReason::Ok);
+                let accepted = reasons.is_empty();
 
-                let (wire_code, wire_detail, wire_ctx) =
-                    map_local_to_wire(&reason_enum, fee_tier.as_str(), min_avg_fee_used, &cfg);
+                let feerate = template_feerate_sat_vb(&propose);
+                let min_feerate_used = cfg.min_feerate_for_tier(&fee_tier);
+
+                let mut ctx = build_policy_context(
+                    fee_tier.as_str(),
+                    min_avg_fee_used,
+                    feerate,
+                    min_feerate_used,
+                    tier_changed_at_unix_ms,
+                    cfg.tier_metric_value(mempool.as_ref()),
+                    &cfg,
+                );
+                ctx.chain_tip_hash = tip.as_ref().map(|t| t.hash.clone());
+                ctx.chain_tip_height = tip.as_ref().map(|t| t.height);
+                ctx.policy_version = Some(policy_version);
+                ctx.overlay = overlay.clone();
+                let violations: Vec<Violation> =
+                    reasons.iter().filter_map(map_local_to_wire).collect();
+
+                let wire_code = violations.first().map(|v| v.reason_code.clone());
+                let wire_detail = violations.first().and_then(|v| v.reason_detail.clone());
 
                 let reason_code_str: Option<String> = wire_code
                     .as_ref()
@@ -436,6 +824,34 @@ async fn run_tcp_server(
 
                 let reason_detail_str: Option<String> = wire_detail.clone();
 
+                let violation_codes: Vec<String> = violations
+                    .iter()
+                    .map(|v| wire_reason_code_str(&v.reason_code).to_string())
+                    .collect();
+
+                let warnings: Vec<Violation> = warning_reasons
+                    .iter()
+                    .filter_map(map_local_to_wire)
+                    .collect();
+                let warning_codes: Vec<String> = warnings
+                    .iter()
+                    .map(|v| wire_reason_code_str(&v.reason_code).to_string())
+                    .collect();
+
+                let shadow_outcome: Option<(bool, String)> = {
+                    let mut guard = state_clone.shadow.lock().unwrap();
+                    guard.as_mut().map(|shadow| {
+                        evaluate_shadow(
+                            shadow,
+                            &propose,
+                            mempool.as_ref(),
+                            eval_ctx,
+                            evaluated_at_ms,
+                            reason_code_str.as_deref().unwrap_or("ok"),
+                        )
+                    })
+                };
+
                 let avg_fee = compute_avg_fee_sats_per_tx(&propose);
 
                 let verdict = TemplateVerdict {
@@ -444,7 +860,17 @@ async fn run_tcp_server(
                     accepted,
                     reason_code: wire_code,
                     reason_detail: wire_detail.clone(),
-                    policy_context: wire_ctx,
+                    policy_context: caps.contains(&Capability::PolicyContext).then_some(ctx),
+                    violations: if caps.contains(&Capability::Violations) {
+                        violations
+                    } else {
+                        Vec::new()
+                    },
+                    warnings: if caps.contains(&Capability::Warnings) {
+                        warnings
+                    } else {
+                        Vec::new()
+                    },
                 };
 
                 let log_id: u64 = id_ctr.fetch_add(1, Ordering::Relaxed);
@@ -471,6 +897,28 @@ async fn run_tcp_server(
                     min_avg_fee_used,
                     fee_tier: fee_tier.as_str().to_string(),
                     avg_fee_sats_per_tx: avg_fee,
+                    observed_weight: propose.observed_weight,
+                    feerate_sat_vb: feerate,
+                    min_feerate_used,
+                    violation_codes,
+                    coinbase_value: propose.coinbase_value,
+                    coinbase_value_substituted: propose.coinbase_value_substituted,
+                    created_at_unix_ms: propose.created_at_unix_ms,
+                    latency_ms: propose
+                        .created_at_unix_ms
+                        .map(|c| current_timestamp_ms().saturating_sub(c)),
+                    shadow_accepted: shadow_outcome.as_ref().map(|(a, _)| *a),
+                    shadow_reason_code: shadow_outcome.map(|(_, code)| code),
+                    input: Some(VerdictInput {
+                        template: propose.clone(),
+                        mempool,
+                        chain_tip: tip,
+                        received_at_unix_ms: received_at_ms,
+                        evaluated_at_unix_ms: evaluated_at_ms,
+                    }),
+                    policy_version: Some(policy_version),
+                    warning_codes,
+                    overlay,
                 };
 
                 {
@@ -488,21 +936,8 @@ async fn run_tcp_server(
                     append_verdict_to_disk(&logged_for_disk);
                 });
 
-                let json = match serde_json::to_string(&verdict) {
-                    Ok(j) => j,
-                    Err(e) => {
-                        eprintln!("serialize verdict error: {e:?}");
-                        break;
-                    }
-                };
-
-                if writer.write_all(json.as_bytes()).await.is_err() {
-                    break;
-                }
-                if writer.write_all(b"\n").await.is_err() {
-                    break;
-                }
-                if writer.flush().await.is_err() {
+                if let Err(e) = framed.send(&verdict).await {
+                    eprintln!("write verdict error: {e:#}");
                     break;
                 }
             }
@@ -784,6 +1219,15 @@ static INDEX_HTML: &str = r##"<!doctype html>
       border-color: #7aa2ff;
       box-shadow: 0 0 0 1px rgba(122,162,255,0.5);
     }
+    .wizard-tiers td {
+      padding: 2px 6px 2px 0;
+    }
+    .wizard-input.field-error {
+      border-color: #ff6b6b;
+    }
+    .wizard-input.field-warning {
+      border-color: #f5c060;
+    }
     .wizard-output {
       width: 100%;
       margin-top: 8px;
@@ -880,12 +1324,13 @@ static INDEX_HTML: &str = r##"<!doctype html>
               <th style="width: 60px;">height</th>
               <th style="width: 40px;">tier</th>
               <th style="width: 50px;">ratio</th>
+              <th style="width: 60px;">sat/vB</th>
               <th style="width: 70px;">decision</th>
               <th>reason</th>
             </tr>
           </thead>
           <tbody id="table-latest">
-            <tr><td class="muted" colspan="7">no verdicts yet</td></tr>
+            <tr><td class="muted" colspan="8">no verdicts yet</td></tr>
           </tbody>
         </table>
       </div>
@@ -901,6 +1346,15 @@ static INDEX_HTML: &str = r##"<!doctype html>
           </tbody>
         </table>
 
+        <table style="margin-top:14px;">
+          <thead>
+            <tr><th>all violations</th><th style="width:60px;">count</th></tr>
+          </thead>
+          <tbody id="table-violations">
+            <tr><td class="muted" colspan="2">no violations yet</td></tr>
+          </tbody>
+        </table>
+
         <table style="margin-top:14px;">
           <thead>
             <tr><th>tier</th><th style="width:60px;">count</th></tr>
@@ -938,27 +1392,22 @@ static INDEX_HTML: &str = r##"<!doctype html>
           </p>
 
           <div id="wizard-panel" style="display:none;margin-top:10px;">
+            <div class="wizard-label">Fee tiers, lowest first (a tier applies once mempool ≥ its threshold)</div>
+            <table class="wizard-tiers">
+              <thead>
+                <tr>
+                  <th>name</th>
+                  <th>mempool ≥</th>
+                  <th>floor sats/tx</th>
+                  <th>floor sat/vB</th>
+                  <th style="width:60px;"></th>
+                </tr>
+              </thead>
+              <tbody id="wiz-tiers"></tbody>
+            </table>
+            <button id="btn-add-tier" class="badge badge-click" style="margin-top:6px;">add tier</button>
+
             <div class="wizard-grid">
-              <div>
-                <div class="wizard-label">Low tier mempool &lt; tx</div>
-                <input id="wiz-low-mempool" class="wizard-input" type="number" min="0" />
-              </div>
-              <div>
-                <div class="wizard-label">High tier mempool ≥ tx</div>
-                <input id="wiz-high-mempool" class="wizard-input" type="number" min="0" />
-              </div>
-              <div>
-                <div class="wizard-label">Low tier floor sats/tx</div>
-                <input id="wiz-fee-lo" class="wizard-input" type="number" min="0" />
-              </div>
-              <div>
-                <div class="wizard-label">Mid tier floor sats/tx</div>
-                <input id="wiz-fee-mid" class="wizard-input" type="number" min="0" />
-              </div>
-              <div>
-                <div class="wizard-label">High tier floor sats/tx</div>
-                <input id="wiz-fee-hi" class="wizard-input" type="number" min="0" />
-              </div>
               <div>
                 <div class="wizard-label">Min total fees sats</div>
                 <input id="wiz-min-total" class="wizard-input" type="number" min="0" />
@@ -967,6 +1416,15 @@ static INDEX_HTML: &str = r##"<!doctype html>
                 <div class="wizard-label">Max tx count</div>
                 <input id="wiz-max-tx" class="wizard-input" type="number" min="0" />
               </div>
+              <div>
+                <div class="wizard-label">Tier metric</div>
+                <select id="wiz-tier-metric" class="wizard-input">
+                  <option value="tx_count">tx count</option>
+                  <option value="vbytes">vbytes</option>
+                  <option value="usage_ratio">usage / maxmempool</option>
+                  <option value="mempool_min_fee">mempoolminfee sat/vB</option>
+                </select>
+              </div>
             </div>
 
             <div style="margin-top:10px;display:flex;gap:8px;align-items:center;">
@@ -1033,13 +1491,9 @@ static INDEX_HTML: &str = r##"<!doctype html>
       }
 
       var wizardIds = [
-        "wiz-low-mempool",
-        "wiz-high-mempool",
-        "wiz-fee-lo",
-        "wiz-fee-mid",
-        "wiz-fee-hi",
         "wiz-min-total",
         "wiz-max-tx",
+        "wiz-tier-metric",
       ];
 
       wizardIds.forEach(function (id) {
@@ -1050,6 +1504,22 @@ static INDEX_HTML: &str = r##"<!doctype html>
         });
       });
 
+      var addTier = document.getElementById("btn-add-tier");
+      if (addTier) {
+        addTier.addEventListener("click", function () {
+          var tiers = readWizardTiers();
+          var last = tiers.length > 0 ? tiers[tiers.length - 1] : null;
+          tiers.push({
+            name: "tier" + tiers.length,
+            threshold: last ? last.threshold : 0,
+            min_avg_fee: last ? last.min_avg_fee : 0,
+            min_feerate: last ? last.min_feerate : 0,
+          });
+          renderWizardTiers(tiers);
+          wizardDirty = true;
+        });
+      }
+
       // generate button stays as before
       var gen = document.getElementById("btn-generate-toml");
       if (gen) {
@@ -1071,13 +1541,77 @@ static INDEX_HTML: &str = r##"<!doctype html>
           el.value = value;
         }
       }
-      setVal("wiz-low-mempool", policy.low_mempool_tx);
-      setVal("wiz-high-mempool", policy.high_mempool_tx);
-      setVal("wiz-fee-lo",      policy.min_avg_fee_lo);
-      setVal("wiz-fee-mid",     policy.min_avg_fee_mid);
-      setVal("wiz-fee-hi",      policy.min_avg_fee_hi);
+      if (Array.isArray(policy.tiers)) renderWizardTiers(policy.tiers);
       setVal("wiz-min-total",   policy.min_total_fees);
       setVal("wiz-max-tx",      policy.max_tx_count);
+      setVal("wiz-tier-metric", policy.tier_metric);
+    }
+
+    function renderWizardTiers(tiers) {
+      var tbody = document.getElementById("wiz-tiers");
+      if (!tbody) return;
+      while (tbody.firstChild) tbody.removeChild(tbody.firstChild);
+
+      tiers.forEach(function (t, idx) {
+        var tr = document.createElement("tr");
+
+        function cell(field, type, value, step) {
+          var td = document.createElement("td");
+          var input = document.createElement("input");
+          input.className = "wizard-input";
+          input.type = type;
+          if (type === "number") input.min = "0";
+          if (step) input.step = step;
+          input.value = value === undefined || value === null ? "" : value;
+          input.dataset.field = field;
+          input.addEventListener("input", function () { wizardDirty = true; });
+          td.appendChild(input);
+          tr.appendChild(td);
+        }
+
+        cell("name", "text", t.name);
+        cell("threshold", "number", t.threshold);
+        cell("min_avg_fee", "number", t.min_avg_fee);
+        cell("min_feerate", "number", t.min_feerate, "0.1");
+
+        var tdDel = document.createElement("td");
+        var del = document.createElement("button");
+        del.className = "badge badge-click";
+        del.textContent = "remove";
+        del.addEventListener("click", function () {
+          var current = readWizardTiers();
+          current.splice(idx, 1);
+          renderWizardTiers(current);
+          wizardDirty = true;
+        });
+        tdDel.appendChild(del);
+        tr.appendChild(tdDel);
+
+        tbody.appendChild(tr);
+      });
+    }
+
+    function readWizardTiers() {
+      var tbody = document.getElementById("wiz-tiers");
+      if (!tbody) return [];
+
+      var tiers = [];
+      Array.prototype.forEach.call(tbody.querySelectorAll("tr"), function (tr) {
+        var t = {};
+        Array.prototype.forEach.call(tr.querySelectorAll("input"), function (input) {
+          var f = input.dataset.field;
+          var v = input.value.trim();
+          if (f === "name") {
+            t.name = v;
+          } else {
+            var n = v === "" ? 0 : Number(v);
+            t[f] = (!isFinite(n) || n < 0) ? 0 : n;
+          }
+        });
+        t.min_avg_fee = Math.floor(t.min_avg_fee || 0);
+        tiers.push(t);
+      });
+      return tiers;
     }
 
     function generateTomlFromWizard() {
@@ -1088,13 +1622,11 @@ static INDEX_HTML: &str = r##"<!doctype html>
         return v === "" ? null : Number(v);
       }
 
-      var low   = getNum("wiz-low-mempool");
-      var high  = getNum("wiz-high-mempool");
-      var flo   = getNum("wiz-fee-lo");
-      var fmid  = getNum("wiz-fee-mid");
-      var fhi   = getNum("wiz-fee-hi");
+      var tiers = readWizardTiers();
       var minT  = getNum("wiz-min-total");
       var maxTx = getNum("wiz-max-tx");
+      var metricEl = document.getElementById("wiz-tier-metric");
+      var tierMetric = metricEl ? metricEl.value : "tx_count";
 
       // Build TOML text just for display / copy
       var lines = [];
@@ -1105,22 +1637,27 @@ static INDEX_HTML: &str = r##"<!doctype html>
 
       // defaults when inputs are empty
       function or0(x) { return (x == null || !isFinite(x)) ? 0 : Math.floor(x); }
+      function fmtFloat(x) { return Number.isInteger(x) ? x.toFixed(1) : String(x); }
       function orMaxU32(x) { return (x == null || !isFinite(x)) ? 4294967295 : Math.floor(x); }
 
       lines.push("min_total_fees = " + String(or0(minT)));
       lines.push("max_tx_count = " + String(orMaxU32(maxTx)));
 
-      // tier thresholds and floors
-      lines.push("low_mempool_tx = " + String(or0(low)));
-      lines.push("high_mempool_tx = " + String(or0(high)));
-      lines.push("min_avg_fee_lo = " + String(or0(flo)));
-      lines.push("min_avg_fee_mid = " + String(or0(fmid)));
-      lines.push("min_avg_fee_hi = " + String(or0(fhi)));
-
       // flags and safety (keep explicit so export is complete)
       lines.push("reject_empty_templates = true");
       lines.push("reject_coinbase_zero = false");
       lines.push("unknown_mempool_as_high = true");
+      lines.push("tier_metric = " + JSON.stringify(tierMetric));
+
+      // fee ladder, lowest tier first
+      tiers.forEach(function (t) {
+        lines.push("");
+        lines.push("[[policy.tiers]]");
+        lines.push("name = " + JSON.stringify(t.name));
+        lines.push("threshold = " + fmtFloat(t.threshold));
+        lines.push("min_avg_fee = " + String(t.min_avg_fee));
+        lines.push("min_feerate = " + fmtFloat(t.min_feerate));
+      });
 
       lines.push("");
       lines.push("[policy.safety]");
@@ -1138,23 +1675,27 @@ static INDEX_HTML: &str = r##"<!doctype html>
         method: "POST",
         headers: { "Content-Type": "application/json" },
         body: JSON.stringify({
-          low_mempool_tx:   low,
-          high_mempool_tx:  high,
-          min_avg_fee_lo:   flo,
-          min_avg_fee_mid:  fmid,
-          min_avg_fee_hi:   fhi,
+          tiers:            tiers,
+          tier_metric:      tierMetric,
           min_total_fees:   minT,
           max_tx_count:     maxTx,
         }),
       })
         .then(function (res) {
-          return res.text().then(function (text) {
+          return res.json().then(function (body) {
+            var issues = (body.validation && body.validation.issues) || [];
+            markWizardIssues(issues);
+            var warnings = issues.filter(function (i) { return i.severity === "warning"; });
             if (res.ok) {
               // backend accepted and validated the new config
               wizardDirty = false; // allow next /policy to repopulate debug, etc.
-              if (status) status.textContent = "policy applied";
+              if (status) {
+                status.textContent = warnings.length
+                  ? "policy applied with " + warnings.length + " warning(s): " + warnings[0].message
+                  : "policy applied";
+              }
             } else {
-              if (status) status.textContent = "error: " + text;
+              if (status) status.textContent = "error: " + (body.error || res.status);
             }
           });
         })
@@ -1164,6 +1705,42 @@ static INDEX_HTML: &str = r##"<!doctype html>
         });
     }
 
+    // Map a validation path ("tiers[1].min_avg_fee", "max_tx_count") to its wizard input.
+    function wizardInputForPath(path) {
+      var m = /^tiers\[(\d+)\]\.(\w+)$/.exec(path);
+      if (m) {
+        var rows = document.querySelectorAll("#wiz-tiers tr");
+        var row = rows[Number(m[1])];
+        return row ? row.querySelector('input[data-field="' + m[2] + '"]') : null;
+      }
+      var ids = {
+        min_total_fees: "wiz-min-total",
+        max_tx_count:   "wiz-max-tx",
+        tier_metric:    "wiz-tier-metric",
+      };
+      return ids[path] ? document.getElementById(ids[path]) : null;
+    }
+
+    function markWizardIssues(issues) {
+      Array.prototype.forEach.call(
+        document.querySelectorAll("#wizard-panel .wizard-input"),
+        function (el) {
+          el.classList.remove("field-error", "field-warning");
+          el.removeAttribute("title");
+        }
+      );
+      issues.forEach(function (issue) {
+        var el = wizardInputForPath(issue.path);
+        if (!el) return;
+        // an error on the same field wins over a warning
+        if (issue.severity === "error") el.classList.remove("field-warning");
+        if (!el.classList.contains("field-error")) {
+          el.classList.add(issue.severity === "error" ? "field-error" : "field-warning");
+        }
+        el.title = el.title ? el.title + "\n" + issue.message : issue.message;
+      });
+    }
+
     function renderTable(targetId, map, emptyText) {
       var tbody = document.getElementById(targetId);
       if (!tbody) return;
@@ -1188,7 +1765,8 @@ static INDEX_HTML: &str = r##"<!doctype html>
         var tdKey = document.createElement('td');
         var tdVal = document.createElement('td');
         tdKey.textContent = k;
-        tdKey.className = targetId === "table-reasons" ? "reason-cell" : "";
+        tdKey.className =
+          (targetId === "table-reasons" || targetId === "table-violations") ? "reason-cell" : "";
         tdVal.textContent = map[k];
         tr.appendChild(tdKey);
         tr.appendChild(tdVal);
@@ -1238,7 +1816,7 @@ static INDEX_HTML: &str = r##"<!doctype html>
       if (!Array.isArray(verdicts) || verdicts.length === 0) {
         var tr = document.createElement("tr");
         var td = document.createElement("td");
-        td.colSpan = 7;
+        td.colSpan = 8;
         td.textContent = "no verdicts yet";
         td.className = "muted";
         tr.appendChild(td);
@@ -1278,19 +1856,27 @@ static INDEX_HTML: &str = r##"<!doctype html>
         tdRatio.textContent = ratioText;
         tdRatio.className = "mono";
 
+        var tdFeerate = document.createElement("td");
+        tdFeerate.textContent =
+          typeof v.feerate_sat_vb === "number" ? v.feerate_sat_vb.toFixed(2) : "n/a";
+        tdFeerate.className = "mono";
+
         var tdDecision = document.createElement("td");
         tdDecision.textContent = v.accepted ? "accepted" : "rejected";
         tdDecision.className = v.accepted ? "tag-accepted" : "tag-rejected";
 
         var tdReason = document.createElement("td");
-        tdReason.textContent = v.reason || "Ok";
+        var extra = Array.isArray(v.violation_codes) ? v.violation_codes.length - 1 : 0;
+        tdReason.textContent = (v.reason || "Ok") + (extra > 0 ? " (+" + extra + ")" : "");
         tdReason.className = v.reason ? "" : "muted";
+        if (extra > 0) tdReason.title = v.violation_codes.join(", ");
 
         tr.appendChild(tdTime);
         tr.appendChild(tdId);
         tr.appendChild(tdHeight);
         tr.appendChild(tdTier);
         tr.appendChild(tdRatio);
+        tr.appendChild(tdFeerate);
         tr.appendChild(tdDecision);
         tr.appendChild(tdReason);
 
@@ -1353,6 +1939,7 @@ static INDEX_HTML: &str = r##"<!doctype html>
         const accepted = data.accepted || 0;
         const rejected = data.rejected || 0;
         const byReason = data.by_reason || {};
+        const byViolation = data.by_violation || {};
         const byTier   = data.by_tier   || {};
         const last     = data.last      || null;
 
@@ -1365,18 +1952,27 @@ static INDEX_HTML: &str = r##"<!doctype html>
         if (pill) pill.textContent = "accept rate " + fmtPercent(rate);
 
         renderTable("table-reasons", byReason, "no verdicts yet");
+        renderTable("table-violations", byViolation, "no violations yet");
         renderTable("table-tiers",   byTier,   "no tiers yet");
         renderPillsForTiers(byTier);
 
         if (last) {
         const labelTier = last.fee_tier || "unknown";
-        const labelFeeUsed =
+        let labelFeeUsed =
             typeof last.min_avg_fee_used === "number"
             ? (last.min_avg_fee_used + " sats per tx")
             : "n/a";
+        if (typeof last.min_feerate_used === "number" && last.min_feerate_used > 0) {
+            labelFeeUsed += ", " + last.min_feerate_used + " sat/vB";
+        }
 
-        setText("metric-tier",        labelTier);
-        setText("metric-tier-detail", "floor " + labelFeeUsed);
+        let tierSince = "";
+        if (typeof data.tier_changed_at_unix_ms === "number") {
+            tierSince = " · since " + fmtTime(data.tier_changed_at_unix_ms / 1000);
+        }
+
+        setText("metric-tier",        data.current_tier || labelTier);
+        setText("metric-tier-detail", "floor " + labelFeeUsed + tierSince);
 
         const resultLabel = last.accepted ? "accepted" : "rejected";
         const resultElem  = document.getElementById("metric-latest-result");
@@ -1388,10 +1984,13 @@ static INDEX_HTML: &str = r##"<!doctype html>
         setText("pill-latest-id", "id " + (last.log_id ?? ""));
         setText("pill-latest-height", "height " + last.height);
 
-        const avgFeeLine =
+        let avgFeeLine =
             typeof last.avg_fee_sats_per_tx === "number"
             ? ("avg fee " + last.avg_fee_sats_per_tx + " sats/tx")
             : "avg fee n/a";
+        if (typeof last.feerate_sat_vb === "number") {
+            avgFeeLine += " · " + last.feerate_sat_vb.toFixed(2) + " sat/vB";
+        }
         setText("metric-latest-fee", avgFeeLine);
         } else {
         setText("metric-tier",             "none");
@@ -1402,23 +2001,51 @@ static INDEX_HTML: &str = r##"<!doctype html>
         setText("pill-latest-height",      "height n/a");
         }
 
+        const metric = (policy && policy.tier_metric) || "tx_count";
+
+        function metricUnit(m) {
+          if (m === "vbytes") return " vB";
+          if (m === "usage_ratio") return " of maxmempool";
+          if (m === "mempool_min_fee") return " sat/vB minfee";
+          return " tx";
+        }
+
+        // Mirrors TierMetric::value on the verifier side.
+        function metricValue(m, mp) {
+          if (!mp || mp.error) return null;
+          if (m === "vbytes") return typeof mp.bytes === "number" ? mp.bytes : null;
+          if (m === "usage_ratio") {
+            return (typeof mp.usage === "number" && typeof mp.max === "number" && mp.max > 0)
+              ? mp.usage / mp.max : null;
+          }
+          if (m === "mempool_min_fee") {
+            return typeof mp.min_relay_fee === "number" ? mp.min_relay_fee / 1000 : null;
+          }
+          return typeof mp.tx_count === "number" ? mp.tx_count : null;
+        }
+
         const pEl = document.getElementById("policy-debug");
         if (pEl) {
         if (policy) {
-            const lo   = policy.low_mempool_tx;
-            const hi   = policy.high_mempool_tx;
-            const flo  = policy.min_avg_fee_lo;
-            const fmid = policy.min_avg_fee_mid;
-            const fhi  = policy.min_avg_fee_hi;
+            const tiers = Array.isArray(policy.tiers) ? policy.tiers : [];
+
+            function floorLabel(sats, rate) {
+              return "floor " + sats + " sats/tx" + (rate > 0 ? ", " + rate + " sat/vB" : "");
+            }
 
             const lines = [];
 
-            if (typeof lo === "number" && typeof hi === "number") {
-              const rows = [
-                { tier: "low",  window: "mempool < " + lo + " tx",        floor: "floor " + flo + " sats/tx" },
-                { tier: "mid",  window: lo + " ≤ mempool < " + hi + " tx", floor: "floor " + fmid + " sats/tx" },
-                { tier: "high", window: "mempool ≥ " + hi + " tx",        floor: "floor " + fhi + " sats/tx" },
-              ];
+            if (tiers.length > 0) {
+              const rows = tiers.map(function (t, i) {
+                const next = tiers[i + 1];
+                let window;
+                const unit = metricUnit(metric);
+                if (i === 0 && next) window = "mempool < " + next.threshold + unit;
+                else if (next) window = t.threshold + " ≤ mempool < " + next.threshold + unit;
+                else if (i === 0) window = "any mempool";
+                else window = "mempool ≥ " + t.threshold + unit;
+                return { tier: t.name, window: window, floor: floorLabel(t.min_avg_fee, t.min_feerate || 0) };
+              });
 
               function pad(str, width) { return str + " ".repeat(Math.max(0, width - str.length)); }
 
@@ -1426,7 +2053,7 @@ static INDEX_HTML: &str = r##"<!doctype html>
               const col2 = Math.max("window".length, ...rows.map(r => r.window.length));
               const col3 = Math.max("floor".length, ...rows.map(r => r.floor.length));
 
-              lines.push("Tier logic (by mempool tx count)");
+              lines.push("Tier logic (by mempool " + metric + ")");
               lines.push("");
               lines.push("  " + pad("tier", col1) + "   " + pad("window", col2) + "   " + pad("floor", col3));
               lines.push("  " + "-".repeat(col1) + "   " + "-".repeat(col2) + "   " + "-".repeat(col3));
@@ -1438,6 +2065,9 @@ static INDEX_HTML: &str = r##"<!doctype html>
               lines.push("Other constraints");
               lines.push("  min_total_fees = " + policy.min_total_fees + " sats");
               lines.push("  max_tx_count   = " + policy.max_tx_count);
+              if (typeof policy.max_block_weight === "number") {
+                lines.push("  max_weight     = " + policy.max_block_weight + " WU (ratio " + policy.max_weight_ratio + ")");
+              }
             }
 
             if (lines.length === 0 && typeof policy.debug === "string") {
@@ -1483,14 +2113,12 @@ static INDEX_HTML: &str = r##"<!doctype html>
           if (bytesEl) bytesEl.textContent = usageStr + staleInfo;
 
           let tierLabel = "n/a";
-          if (policy) {
-            const lo = policy.low_mempool_tx;
-            const hi = policy.high_mempool_tx;
-            if (typeof lo === "number" && typeof hi === "number") {
-              if (tx < lo) tierLabel = "low";
-              else if (tx < hi) tierLabel = "mid";
-              else tierLabel = "high";
-            }
+          const sample = metricValue(metric, mempool);
+          if (sample !== null && policy && Array.isArray(policy.tiers) && policy.tiers.length > 0) {
+            tierLabel = policy.tiers[0].name;
+            policy.tiers.forEach(function (t) {
+              if (sample >= t.threshold) tierLabel = t.name;
+            });
           }
 
           if (tierEl) tierEl.textContent = "expected tier " + tierLabel;
@@ -1507,6 +2135,12 @@ static INDEX_HTML: &str = r##"<!doctype html>
         if (status) {
         const now = new Date();
         status.innerHTML = "Last update: <span>" + now.toLocaleTimeString() + "</span>";
+        if (meta && meta.policy_file_error) {
+            const warn = document.createElement("span");
+            warn.textContent = " \u00b7 policy file rejected, previous policy kept";
+            warn.title = meta.policy_file_error.error;
+            status.appendChild(warn);
+        }
         }
     } catch (err) {
         const status = document.getElementById("status-line");
@@ -1564,7 +2198,17 @@ async fn run_http_server(
         .route("/policy", get(get_policy))
         .route("/policy/apply", post(apply_policy))
         .route("/policy/apply_toml", post(apply_policy_toml))
+        .route("/policy/history", get(get_policy_history))
+        .route("/policy/history/:version", get(get_policy_revision))
+        .route("/policy/rollback/:version", post(rollback_policy))
+        .route(
+            "/policy/shadow",
+            get(get_shadow_policy)
+                .post(set_shadow_policy)
+                .delete(clear_shadow_policy),
+        )
         .route("/mempool", get(get_mempool_proxy))
+        .route("/chain/tip", get(get_chain_tip).post(push_chain_tip))
         .route("/meta", get(get_meta))
         .with_state(app_state.clone())
         .layer(Extension(verdict_log))
@@ -1628,7 +2272,7 @@ async fn get_verdicts_csv(
     let start = log.len().saturating_sub(limit);
 
     let mut out = String::new();
-    out.push_str("log_id,template_id,height,total_fees,tx_count,accepted,fee_tier,min_avg_fee_used,avg_fee_sats_per_tx,reason_code,reason_detail,reason,timestamp\n");
+    out.push_str("log_id,template_id,height,total_fees,tx_count,accepted,fee_tier,min_avg_fee_used,avg_fee_sats_per_tx,feerate_sat_vb,min_feerate_used,observed_weight,reason_code,reason_detail,reason,violations,latency_ms,timestamp\n");
 
     for v in log.iter().skip(start) {
         let reason_code = v
@@ -1642,10 +2286,19 @@ async fn get_verdicts_csv(
         let reason = v.reason.as_deref().unwrap_or("ok");
         let escaped_reason = reason.replace('"', "\"\"");
 
+        let violations = v.violation_codes.join(";");
+
+        let observed_weight = v.observed_weight.map(|w| w.to_string()).unwrap_or_default();
+        let latency_ms = v.latency_ms.map(|l| l.to_string()).unwrap_or_default();
+        let feerate = v
+            .feerate_sat_vb
+            .map(|f| format!("{f:.3}"))
+            .unwrap_or_default();
+
         use std::fmt::Write as _;
         let _ = writeln!(
             out,
-            "{},{},{},{},{},{},{},{},{},\"{}\",\"{}\",\"{}\",{}",
+            "{},{},{},{},{},{},{},{},{},{},{},{},\"{}\",\"{}\",\"{}\",\"{}\",{},{}",
             v.log_id,
             v.template_id,
             v.height,
@@ -1655,9 +2308,14 @@ async fn get_verdicts_csv(
             v.fee_tier,
             v.min_avg_fee_used,
             v.avg_fee_sats_per_tx,
+            feerate,
+            v.min_feerate_used,
+            observed_weight,
             escaped_code,
             escaped_detail,
             escaped_reason,
+            violations,
+            latency_ms,
             v.timestamp,
         );
     }
@@ -1668,13 +2326,36 @@ async fn get_verdicts_csv(
 async fn apply_policy(
     State(app_state): State<AppState>,
     Json(req): Json<ApplyPolicyReq>,
-) -> impl IntoResponse {
-    let base_cfg = {
+) -> (StatusCode, Json<serde_json::Value>) {
+    // Partial edit: committed only if no other change landed in between.
+    let (base_cfg, base_version) = {
         let holder = app_state.policy.read().unwrap();
-        holder.config.clone()
+        (holder.config.clone(), holder.version)
     };
 
     let mut cfg = base_cfg;
+
+    let legacy_tier_fields = req.low_mempool_tx.is_some()
+        || req.high_mempool_tx.is_some()
+        || req.min_avg_fee_lo.is_some()
+        || req.min_avg_fee_mid.is_some()
+        || req.min_avg_fee_hi.is_some()
+        || req.min_feerate_lo.is_some()
+        || req.min_feerate_mid.is_some()
+        || req.min_feerate_hi.is_some();
+    if legacy_tier_fields && req.tiers.is_none() && !cfg.tiers.is_empty() {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(json!({
+                "ok": false,
+                "error": "policy uses [[policy.tiers]]; send `tiers` instead of lo/mid/hi fields",
+            })),
+        );
+    }
+
+    if let Some(tiers) = req.tiers {
+        cfg.tiers = tiers;
+    }
     if let Some(v) = req.low_mempool_tx {
         cfg.low_mempool_tx = v;
     }
@@ -1690,18 +2371,28 @@ async fn apply_policy(
     if let Some(v) = req.min_avg_fee_hi {
         cfg.min_avg_fee_hi = v;
     }
+    if let Some(v) = req.min_feerate_lo {
+        cfg.min_feerate_lo = v;
+    }
+    if let Some(v) = req.min_feerate_mid {
+        cfg.min_feerate_mid = v;
+    }
+    if let Some(v) = req.min_feerate_hi {
+        cfg.min_feerate_hi = v;
+    }
     if let Some(v) = req.min_total_fees {
         cfg.min_total_fees = v;
     }
     if let Some(v) = req.max_tx_count {
         cfg.max_tx_count = v;
     }
+    if let Some(v) = req.tier_metric {
+        cfg.tier_metric = v;
+    }
 
-    if let Err(e) = cfg.validate() {
-        return (
-            StatusCode::BAD_REQUEST,
-            format!("policy validation failed: {e:?}"),
-        );
+    let report = cfg.validation_report();
+    if report.has_errors() {
+        return validation_failed(report);
     }
 
     #[derive(Serialize)]
@@ -1709,16 +2400,87 @@ async fn apply_policy(
         policy: &'a PolicyConfig,
     }
 
-    let toml_text = toml::to_string_pretty(&Wrapper { policy: &cfg })
-        .unwrap_or_else(|_| "# policy serialization failed\n".to_string());
+    // Never write a placeholder over the policy file; the next startup would load it.
+    let toml_text = match toml::to_string_pretty(&Wrapper { policy: &cfg }) {
+        Ok(t) => t,
+        Err(e) => {
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({
+                    "ok": false,
+                    "error": format!("policy not applied, serialization failed: {}", e),
+                })),
+            );
+        }
+    };
 
+    match app_state
+        .commit_policy(
+            cfg,
+            toml_text,
+            PolicySource::Apply,
+            None,
+            Some(base_version),
+        )
+        .await
     {
-        let mut holder = app_state.policy.write().unwrap();
-        holder.config = cfg;
-        holder.toml_text = toml_text;
+        Ok(version) => (
+            StatusCode::OK,
+            Json(json!({ "ok": true, "version": version, "validation": report })),
+        ),
+        Err(e) => commit_failed("policy not applied", e),
     }
+}
 
-    (StatusCode::OK, "ok".to_string())
+/// 409 when the policy moved on under a partial apply (the client should re-read
+/// and retry), 500 when persisting failed.
+fn commit_failed(what: &str, e: CommitError) -> (StatusCode, Json<serde_json::Value>) {
+    let status = match e {
+        CommitError::Conflict { .. } => StatusCode::CONFLICT,
+        CommitError::Persist(_) => StatusCode::INTERNAL_SERVER_ERROR,
+    };
+    (
+        status,
+        Json(json!({
+            "ok": false,
+            "error": format!("{}, {}", what, e),
+        })),
+    )
+}
+
+/// 400 response carrying the full report, so the wizard can mark each field.
+fn validation_failed(report: ValidationReport) -> (StatusCode, Json<serde_json::Value>) {
+    let summary: Vec<&str> = report.errors().map(|i| i.message.as_str()).collect();
+    (
+        StatusCode::BAD_REQUEST,
+        Json(json!({
+            "ok": false,
+            "error": format!("policy validation failed: {}", summary.join("; ")),
+            "validation": report,
+        })),
+    )
+}
+
+/// Parse, lint and validate a policy TOML body for the apply endpoints.
+fn check_policy_toml(
+    body: &str,
+    strict: bool,
+) -> Result<(PolicyConfig, PolicyLint, ValidationReport), (StatusCode, Json<serde_json::Value>)> {
+    let policy = crate::state::parse_policy_from_policy_table(body).map_err(|e| {
+        (
+            StatusCode::BAD_REQUEST,
+            Json(json!({
+                "ok": false,
+                "error": format!("toml parse failed: {:#}", e),
+            })),
+        )
+    })?;
+    let lint = lint_policy_text(body);
+    let report = crate::state::policy_report(&policy, &lint, strict);
+    if report.has_errors() {
+        return Err(validation_failed(report));
+    }
+    Ok((policy, lint, report))
 }
 
 async fn get_policy(State(app_state): State<AppState>) -> Json<serde_json::Value> {
@@ -1726,7 +2488,22 @@ async fn get_policy(State(app_state): State<AppState>) -> Json<serde_json::Value
     let policy = &holder.config;
     let dbg = format!("{policy:?}");
 
+    // Overlays are resolved per template; report them against the next block.
+    let now_ms = current_timestamp_ms();
+    let next_height = app_state
+        .chain_tip
+        .read()
+        .unwrap()
+        .as_ref()
+        .map(|t| t.height + 1);
+
     let body = json!({
+        "version": holder.version,
+        "file_error": holder.file_error,
+        "strict": app_state.policy_strict,
+        "unknown_keys": holder.lint.unknown_keys,
+        "defaulted_fields": holder.lint.defaulted_fields,
+        "validation": crate::state::policy_report(policy, &holder.lint, app_state.policy_strict),
         "protocol_version": policy.protocol_version,
         "required_prevhash_len": policy.required_prevhash_len,
         "min_total_fees": policy.min_total_fees,
@@ -1737,11 +2514,28 @@ async fn get_policy(State(app_state): State<AppState>) -> Json<serde_json::Value
         "min_avg_fee_lo": policy.min_avg_fee_lo,
         "min_avg_fee_mid": policy.min_avg_fee_mid,
         "min_avg_fee_hi": policy.min_avg_fee_hi,
+        "min_feerate_lo": policy.min_feerate_lo,
+        "min_feerate_mid": policy.min_feerate_mid,
+        "min_feerate_hi": policy.min_feerate_hi,
+        "tiers": policy.fee_tiers(),
+        "tiers_explicit": !policy.tiers.is_empty(),
+        "tier_metric": policy.tier_metric,
+        "tier_switching": policy.tier_switching,
 
         "max_weight_ratio": policy.safety.max_weight_ratio,
+        "max_block_weight": policy.max_block_weight(),
         "reject_empty_templates": policy.reject_empty_templates,
         "reject_coinbase_zero": policy.reject_coinbase_zero,
+        "reject_stale_tip": policy.reject_stale_tip,
+        "max_template_age_ms": policy.max_template_age_ms,
+        "max_clock_skew_ms": policy.max_clock_skew_ms,
+        "coinbase": policy.coinbase,
+        "rule_modes": policy.rule_modes,
+        "overlays": policy.overlays,
+        "active_overlay": policy.active_overlay(next_height, now_ms).map(|o| &o.name),
+        "next_transition": policy.next_overlay_transition(next_height, now_ms),
         "unknown_mempool_as_high": policy.unknown_mempool_as_high,
+        "evaluation_mode": policy.evaluation_mode,
 
         "debug": dbg
     });
@@ -1749,10 +2543,15 @@ async fn get_policy(State(app_state): State<AppState>) -> Json<serde_json::Value
     Json(body)
 }
 
-async fn get_meta(Extension(ui_mode): Extension<String>) -> Json<serde_json::Value> {
+async fn get_meta(
+    State(app_state): State<AppState>,
+    Extension(ui_mode): Extension<String>,
+) -> Json<serde_json::Value> {
+    let policy_file_error = app_state.policy.read().unwrap().file_error.clone();
     let body = json!({
         "mode": ui_mode,
         "log_write_errors": LOG_WRITE_ERRORS.load(Ordering::Relaxed),
+        "policy_file_error": policy_file_error,
     });
     Json(body)
 }
@@ -1773,13 +2572,58 @@ async fn get_mempool_proxy() -> Json<serde_json::Value> {
     }
 }
 
-async fn get_stats(Extension(log): Extension<VerdictLog>) -> Json<StatsResponse> {
+#[derive(Deserialize)]
+struct PushChainTipReq {
+    hash: String,
+    height: u32,
+}
+
+async fn push_chain_tip(
+    State(app_state): State<AppState>,
+    Json(req): Json<PushChainTipReq>,
+) -> impl IntoResponse {
+    if req.hash.len() != 64 || !req.hash.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return (
+            StatusCode::BAD_REQUEST,
+            "hash must be 64 hex characters".to_string(),
+        );
+    }
+
+    let tip = ChainTip {
+        hash: req.hash,
+        height: req.height,
+        received_at_unix_ms: current_timestamp_ms(),
+    };
+    *app_state.chain_tip.write().unwrap() = Some(tip);
+
+    (StatusCode::OK, "ok".to_string())
+}
+
+async fn get_chain_tip(State(app_state): State<AppState>) -> Json<serde_json::Value> {
+    let tip = app_state.chain_tip.read().unwrap().clone();
+    let max_age_ms = chain_tip_max_age_ms_from_env();
+    let fresh = chain_tip_client::fresh_tip(tip.clone(), max_age_ms).is_some();
+
+    Json(json!({
+        "source": chain_tip_source_from_env().as_str(),
+        "tip": tip,
+        "fresh": fresh,
+        "max_age_ms": max_age_ms,
+    }))
+}
+
+async fn get_stats(
+    State(app_state): State<AppState>,
+    Extension(log): Extension<VerdictLog>,
+) -> Json<StatsResponse> {
+    let tier_state = app_state.tier_state.lock().unwrap().clone();
     let log = log.lock().unwrap();
 
     let mut total = 0_u64;
     let mut accepted = 0_u64;
     let mut rejected = 0_u64;
     let mut by_reason: BTreeMap<String, u64> = BTreeMap::new();
+    let mut by_violation: BTreeMap<String, u64> = BTreeMap::new();
     let mut by_tier: BTreeMap<String, u64> = BTreeMap::new();
 
     for v in log.iter() {
@@ -1800,7 +2644,23 @@ async fn get_stats(Extension(log): Extension<VerdictLog>) -> Json<StatsResponse>
             "unknown".to_string()
         };
 
+        if v.violation_codes.is_empty() {
+            // Old log lines only carry the primary reason.
+            if !v.accepted {
+                *by_violation.entry(reason_key.clone()).or_insert(0) += 1;
+            }
+        } else {
+            for code in &v.violation_codes {
+                *by_violation.entry(normalize_reason_key(code)).or_insert(0) += 1;
+            }
+        }
+
         *by_reason.entry(reason_key).or_insert(0) += 1;
+        for code in &v.warning_codes {
+            *by_reason
+                .entry(format!("warn:{}", normalize_reason_key(code)))
+                .or_insert(0) += 1;
+        }
         *by_tier.entry(v.fee_tier.clone()).or_insert(0) += 1;
     }
 
@@ -1809,8 +2669,12 @@ async fn get_stats(Extension(log): Extension<VerdictLog>) -> Json<StatsResponse>
         accepted,
         rejected,
         by_reason,
+        by_violation,
         by_tier,
         last: log.last().cloned(),
+        current_tier: tier_state.current.map(|t| t.name),
+        tier_changed_at_unix_ms: tier_state.changed_at_unix_ms,
+        parse_errors: framing::parse_error_counts(),
     })
 }
 
@@ -1821,6 +2685,13 @@ fn current_timestamp() -> u64 {
         .as_secs()
 }
 
+fn current_timestamp_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_millis() as u64
+}
+
 async fn apply_policy_toml(State(app_state): State<AppState>, bytes: Bytes) -> impl IntoResponse {
     let body = match std::str::from_utf8(&bytes) {
         Ok(s) => s.to_string(),
@@ -1835,42 +2706,193 @@ async fn apply_policy_toml(State(app_state): State<AppState>, bytes: Bytes) -> i
         }
     };
 
-    #[derive(Deserialize)]
-    struct Wrapper {
-        policy: PolicyConfig,
+    let (policy, lint, report) = match check_policy_toml(&body, app_state.policy_strict) {
+        Ok(v) => v,
+        Err(resp) => return resp,
+    };
+
+    match app_state
+        .commit_policy(policy, body, PolicySource::ApplyToml, None, None)
+        .await
+    {
+        Ok(version) => (
+            StatusCode::OK,
+            Json(json!({
+                "ok": true,
+                "version": version,
+                "validation": report,
+                "defaulted_fields": lint.defaulted_fields,
+            })),
+        ),
+        Err(e) => commit_failed("policy not applied", e),
     }
+}
 
-    let parsed: Wrapper = match toml::from_str(&body) {
-        Ok(v) => v,
-        Err(e) => {
-            return (
-                StatusCode::BAD_REQUEST,
-                Json(json!({
-                    "ok": false,
-                    "error": format!("toml parse failed: {}", e),
-                })),
-            );
-        }
+async fn get_policy_history(State(app_state): State<AppState>) -> Json<serde_json::Value> {
+    let holder = app_state.policy.read().unwrap();
+
+    // Newest first; TOML bodies are served per version.
+    let revisions: Vec<serde_json::Value> = holder
+        .history
+        .iter()
+        .rev()
+        .map(|r| {
+            json!({
+                "version": r.version,
+                "set_at_unix_ms": r.set_at_unix_ms,
+                "source": r.source,
+                "restored_from": r.restored_from,
+                "active": r.version == holder.version,
+            })
+        })
+        .collect();
+
+    Json(json!({
+        "active_version": holder.version,
+        "revisions": revisions,
+    }))
+}
+
+async fn get_policy_revision(
+    State(app_state): State<AppState>,
+    Path(version): Path<u64>,
+) -> impl IntoResponse {
+    let holder = app_state.policy.read().unwrap();
+    match holder.revision(version) {
+        Some(r) => (
+            StatusCode::OK,
+            Json(json!({
+                "version": r.version,
+                "set_at_unix_ms": r.set_at_unix_ms,
+                "source": r.source,
+                "restored_from": r.restored_from,
+                "active": r.version == holder.version,
+                "policy": r.config,
+                "toml": r.toml_text,
+            })),
+        ),
+        None => (
+            StatusCode::NOT_FOUND,
+            Json(json!({ "error": format!("policy version {} not in history", version) })),
+        ),
+    }
+}
+
+/// Re-apply an earlier revision as a new version, so history stays append-only.
+/// The revision is checked like a fresh `/policy/apply_toml` body, since history
+/// reloaded at startup may predate the current validation rules or protocol.
+async fn rollback_policy(
+    State(app_state): State<AppState>,
+    Path(version): Path<u64>,
+) -> impl IntoResponse {
+    let revision = {
+        let holder = app_state.policy.read().unwrap();
+        holder.revision(version).cloned()
+    };
+    let Some(revision) = revision else {
+        return (
+            StatusCode::NOT_FOUND,
+            Json(json!({
+                "ok": false,
+                "error": format!("policy version {} not in history", version),
+            })),
+        );
     };
 
-    if let Err(e) = parsed.policy.validate() {
+    // The built-in degraded policy has no file form worth restoring.
+    if revision.source == PolicySource::BuiltinDefault {
         return (
             StatusCode::BAD_REQUEST,
             Json(json!({
                 "ok": false,
-                "error": format!("policy validation failed: {:?}", e),
+                "error": "cannot roll back to the built-in degraded policy",
             })),
         );
     }
 
+    let (policy, _, report) = match check_policy_toml(&revision.toml_text, app_state.policy_strict)
     {
-        let mut holder = match app_state.policy.write() {
-            Ok(g) => g,
-            Err(poisoned) => poisoned.into_inner(),
-        };
-        holder.config = parsed.policy;
-        holder.toml_text = body;
+        Ok(v) => v,
+        Err(resp) => return resp,
+    };
+
+    match app_state
+        .commit_policy(
+            policy,
+            revision.toml_text,
+            PolicySource::Rollback,
+            Some(version),
+            None,
+        )
+        .await
+    {
+        Ok(new_version) => (
+            StatusCode::OK,
+            Json(json!({
+                "ok": true,
+                "version": new_version,
+                "restored_from": version,
+                "validation": report,
+            })),
+        ),
+        Err(e) => commit_failed("policy not rolled back", e),
     }
+}
+
+async fn get_shadow_policy(State(app_state): State<AppState>) -> Json<serde_json::Value> {
+    let guard = app_state.shadow.lock().unwrap();
+    let Some(shadow) = guard.as_ref() else {
+        return Json(json!({ "active": false }));
+    };
+
+    Json(json!({
+        "active": true,
+        "set_at_unix_ms": shadow.set_at_unix_ms,
+        "policy": shadow.config,
+        "toml": shadow.toml_text,
+        "current_tier": shadow.tier_state.current.as_ref().map(|t| t.as_str()),
+        "agree": shadow.agree,
+        "disagree": shadow.disagree,
+        "agree_by_reason": shadow.agree_by_reason,
+        "disagree_by_reason": shadow.disagree_by_reason,
+    }))
+}
+
+/// Body is a policy TOML with a `[policy]` table, like `/policy/apply_toml`.
+/// Replacing the shadow policy resets its counters.
+async fn set_shadow_policy(State(app_state): State<AppState>, bytes: Bytes) -> impl IntoResponse {
+    let body = match std::str::from_utf8(&bytes) {
+        Ok(s) => s.to_string(),
+        Err(e) => {
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(json!({
+                    "ok": false,
+                    "error": format!("body must be utf8 text: {}", e),
+                })),
+            );
+        }
+    };
+
+    let (policy, lint, report) = match check_policy_toml(&body, app_state.policy_strict) {
+        Ok(v) => v,
+        Err(resp) => return resp,
+    };
+
+    *app_state.shadow.lock().unwrap() =
+        Some(ShadowPolicy::new(policy, body, current_timestamp_ms()));
+
+    (
+        StatusCode::OK,
+        Json(json!({
+            "ok": true,
+            "validation": report,
+            "defaulted_fields": lint.defaulted_fields,
+        })),
+    )
+}
 
-    (StatusCode::OK, Json(json!({ "ok": true })))
+async fn clear_shadow_policy(State(app_state): State<AppState>) -> Json<serde_json::Value> {
+    let was_active = app_state.shadow.lock().unwrap().take().is_some();
+    Json(json!({ "ok": true, "cleared": was_active }))
 }
@@ -0,0 +1,29 @@
+use serde::{Deserialize, Serialize};
+
+/// One mempool sample as served by template-manager `/mempool`.
+/// Older/alt shapes only carry a count, so everything else is optional.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MempoolSnapshot {
+    // template-manager /mempool => "tx_count"
+    // older/alt shapes may use "count" or "size"
+    #[serde(default, alias = "count", alias = "size")]
+    pub tx_count: u64,
+
+    /// Sum of mempool transaction vsizes (getmempoolinfo "bytes").
+    #[serde(default)]
+    pub bytes: Option<u64>,
+
+    /// Memory usage and its configured cap (getmempoolinfo "usage" / "maxmempool").
+    #[serde(default)]
+    pub usage: Option<u64>,
+    #[serde(default)]
+    pub max: Option<u64>,
+
+    /// mempoolminfee in sat/kvB (template-manager calls it `min_relay_fee`).
+    #[serde(default, alias = "min_relay_fee")]
+    pub min_fee_sat_kvb: Option<u64>,
+
+    // unix seconds if provided
+    #[serde(default)]
+    pub timestamp: Option<u64>,
+}
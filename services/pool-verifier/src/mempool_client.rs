@@ -1,4 +1,4 @@
-use serde::Deserialize;
+use pool_verifier::mempool::MempoolSnapshot;
 use std::sync::OnceLock;
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
@@ -13,18 +13,6 @@ fn client() -> &'static reqwest::Client {
     })
 }
 
-#[derive(Deserialize)]
-struct MempoolSnapshot {
-    // template-manager /mempool => "tx_count"
-    // older/alt shapes may use "count" or "size"
-    #[serde(default, alias = "tx_count", alias = "count", alias = "size")]
-    tx_count: u64,
-
-    // unix seconds if provided
-    #[serde(default)]
-    timestamp: Option<u64>,
-}
-
 pub fn mempool_url_from_env() -> Option<String> {
     std::env::var("VELDRA_MEMPOOL_URL").ok()
 }
@@ -36,7 +24,7 @@ fn now_unix_secs() -> u64 {
         .as_secs()
 }
 
-pub async fn fetch_mempool_tx_count(url: &str) -> Option<u64> {
+pub async fn fetch_mempool_snapshot(url: &str) -> Option<MempoolSnapshot> {
     let resp = match client().get(url).send().await {
         Ok(r) => r,
         Err(e) => {
@@ -69,5 +57,5 @@ pub async fn fetch_mempool_tx_count(url: &str) -> Option<u64> {
         }
     }
 
-    Some(snapshot.tx_count)
+    Some(snapshot)
 }
@@ -1,34 +1,230 @@
-use rg_protocol::{PROTOCOL_VERSION, TemplatePropose};
+use std::collections::BTreeMap;
+
+use rg_protocol::{MAX_BLOCK_WEIGHT, PROTOCOL_VERSION, TemplatePropose};
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
-pub enum FeeTier {
-    Low,
-    Mid,
-    High,
+use crate::chain_tip::ChainTip;
+use crate::mempool::MempoolSnapshot;
+use crate::rules::RuleRegistry;
+use crate::schedule::{PolicyOverlay, is_day_name, parse_hhmm};
+use crate::script_rules::ScriptRule;
+
+/// Tier picked for one evaluation: position in the ladder plus its name.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FeeTier {
+    pub index: usize,
+    pub name: String,
 }
 
 impl FeeTier {
+    pub fn as_str(&self) -> &str {
+        &self.name
+    }
+}
+
+/// Mempool measurement that tier thresholds are compared against.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TierMetric {
+    /// Number of mempool transactions.
+    #[default]
+    TxCount,
+    /// Total mempool vsize in vB.
+    Vbytes,
+    /// Memory usage as a fraction of `maxmempool`, 0.0..=1.0.
+    UsageRatio,
+    /// mempoolminfee in sat/vB.
+    MempoolMinFee,
+}
+
+impl TierMetric {
     pub fn as_str(&self) -> &'static str {
         match self {
-            FeeTier::Low => "low",
-            FeeTier::Mid => "mid",
-            FeeTier::High => "high",
+            TierMetric::TxCount => "tx_count",
+            TierMetric::Vbytes => "vbytes",
+            TierMetric::UsageRatio => "usage_ratio",
+            TierMetric::MempoolMinFee => "mempool_min_fee",
         }
     }
+
+    /// None when the snapshot lacks the field this metric needs.
+    pub fn value(&self, snapshot: &MempoolSnapshot) -> Option<f64> {
+        match self {
+            TierMetric::TxCount => Some(snapshot.tx_count as f64),
+            TierMetric::Vbytes => snapshot.bytes.map(|b| b as f64),
+            TierMetric::UsageRatio => match (snapshot.usage, snapshot.max) {
+                (Some(usage), Some(max)) if max > 0 => Some(usage as f64 / max as f64),
+                _ => None,
+            },
+            TierMetric::MempoolMinFee => snapshot.min_fee_sat_kvb.map(|f| f as f64 / 1000.0),
+        }
+    }
+}
+
+/// One rung of the fee ladder (`[[policy.tiers]]`).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FeeTierConfig {
+    pub name: String,
+
+    /// Lower bound on the `tier_metric` value; the tier applies from here up to the next tier.
+    #[serde(default)]
+    pub threshold: f64,
+
+    /// Floor in sats per tx (total_fees / tx_count). 0 disables the check.
+    #[serde(default)]
+    pub min_avg_fee: u64,
+
+    /// Floor in sat/vB. 0 disables the check.
+    #[serde(default)]
+    pub min_feerate: f64,
+}
+
+/// How many rules `evaluate_violations` runs per template.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EvaluationMode {
+    /// Stop at the first failed rule.
+    #[default]
+    FirstFailure,
+    /// Run every rule and report each failure.
+    All,
 }
 
+/// What a failed rule does (`[policy.rule_modes]`, keyed by reason code).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RuleMode {
+    /// Reject the template.
+    #[default]
+    Enforce,
+    /// Accept, but report the failure as a warning.
+    Warn,
+    /// Skip the rule.
+    Off,
+}
+
+/// Reason codes whose rule can be set to warn or off. Protocol version and
+/// prev_hash shape checks always enforce: a malformed template is never usable.
+pub const CONFIGURABLE_RULES: &[&str] = &[
+    "stale_prev_hash",
+    "height_mismatch",
+    "template_too_old",
+    "template_timestamp_in_future",
+    "empty_template_rejected",
+    "coinbase_value_zero_rejected",
+    "coinbase_value_overclaim",
+    "coinbase_value_underclaim",
+    "tx_count_exceeded",
+    "weight_limit_exceeded",
+    "total_fees_below_minimum",
+    "avg_fee_below_minimum",
+    "feerate_below_minimum",
+];
+
+/// Reason codes of the rules that always enforce.
+pub const FIXED_RULES: &[&str] = &[
+    "protocol_version_mismatch",
+    "prev_hash_len_mismatch",
+    "invalid_prev_hash",
+];
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum VerdictReason {
     Ok,
-    ProtocolVersionMismatch { got: u16, expected: u16 },
-    PrevHashLenMismatch { len: usize, expected: usize },
+    ProtocolVersionMismatch {
+        got: u16,
+        expected: u16,
+    },
+    PrevHashLenMismatch {
+        len: usize,
+        expected: usize,
+    },
     InvalidPrevHash,
+    StalePrevHash {
+        got: String,
+        expected: String,
+    },
+    HeightMismatch {
+        height: u32,
+        expected: u32,
+    },
+    TemplateTooOld {
+        age_ms: u64,
+        max_allowed: u64,
+    },
+    TemplateTimestampInFuture {
+        ahead_ms: u64,
+        max_skew: u64,
+    },
     EmptyTemplateRejected,
-    CoinbaseValueZeroRejected,
-    TotalFeesBelowMinimum { total: u64, min_required: u64 },
-    TxCountExceeded { count: u32, max_allowed: u32 },
-    AvgFeeBelowMinimum { avg: u64, min_required: u64 },
+    CoinbaseValueZeroRejected {
+        substituted: bool,
+    },
+    CoinbaseValueOverclaim {
+        coinbase_value: u64,
+        expected: u64,
+    },
+    CoinbaseValueUnderclaim {
+        coinbase_value: u64,
+        expected: u64,
+        tolerance: u64,
+        /// The backend reported 0 and the sender filled in `coinbase_value`;
+        /// `coinbase_value` is then the backend's 0.
+        substituted: bool,
+    },
+    TotalFeesBelowMinimum {
+        total: u64,
+        min_required: u64,
+    },
+    TxCountExceeded {
+        count: u32,
+        max_allowed: u32,
+    },
+    WeightLimitExceeded {
+        weight: u64,
+        max_allowed: u64,
+    },
+    AvgFeeBelowMinimum {
+        avg: u64,
+        min_required: u64,
+    },
+    FeerateBelowMinimum {
+        feerate: f64,
+        min_required: f64,
+    },
+    /// Failure of a rule outside the built-in set (see `rules::PolicyRule`).
+    Custom {
+        code: String,
+        detail: String,
+        threshold: Option<f64>,
+        observed: Option<f64>,
+    },
+}
+
+impl VerdictReason {
+    /// Stable snake_case code, matching the wire `reason_code` strings.
+    pub fn code(&self) -> &str {
+        match self {
+            VerdictReason::Ok => "ok",
+            VerdictReason::ProtocolVersionMismatch { .. } => "protocol_version_mismatch",
+            VerdictReason::PrevHashLenMismatch { .. } => "prev_hash_len_mismatch",
+            VerdictReason::InvalidPrevHash => "invalid_prev_hash",
+            VerdictReason::StalePrevHash { .. } => "stale_prev_hash",
+            VerdictReason::HeightMismatch { .. } => "height_mismatch",
+            VerdictReason::TemplateTooOld { .. } => "template_too_old",
+            VerdictReason::TemplateTimestampInFuture { .. } => "template_timestamp_in_future",
+            VerdictReason::EmptyTemplateRejected => "empty_template_rejected",
+            VerdictReason::CoinbaseValueZeroRejected { .. } => "coinbase_value_zero_rejected",
+            VerdictReason::CoinbaseValueOverclaim { .. } => "coinbase_value_overclaim",
+            VerdictReason::CoinbaseValueUnderclaim { .. } => "coinbase_value_underclaim",
+            VerdictReason::TotalFeesBelowMinimum { .. } => "total_fees_below_minimum",
+            VerdictReason::TxCountExceeded { .. } => "tx_count_exceeded",
+            VerdictReason::WeightLimitExceeded { .. } => "weight_limit_exceeded",
+            VerdictReason::AvgFeeBelowMinimum { .. } => "avg_fee_below_minimum",
+            VerdictReason::FeerateBelowMinimum { .. } => "feerate_below_minimum",
+            VerdictReason::Custom { code, .. } => code,
+        }
+    }
 }
 
 fn default_max_weight_ratio() -> f64 {
@@ -49,6 +245,58 @@ impl Default for PolicySafety {
     }
 }
 
+fn default_halving_interval() -> u32 {
+    210_000
+}
+
+/// Coinbase value vs. block subsidy + fees (`[policy.coinbase]`). Off by default.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CoinbaseCheck {
+    #[serde(default)]
+    pub enabled: bool,
+
+    /// How far below subsidy + fees coinbase_value may fall before it is an underclaim.
+    #[serde(default)]
+    pub underclaim_tolerance_sats: u64,
+
+    /// Blocks per subsidy halving: 210_000 on mainnet/testnet/signet, 150 on regtest.
+    #[serde(default = "default_halving_interval")]
+    pub halving_interval: u32,
+}
+
+impl Default for CoinbaseCheck {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            underclaim_tolerance_sats: 0,
+            halving_interval: default_halving_interval(),
+        }
+    }
+}
+
+/// Block subsidy in sats at `height`: 50 BTC halving every `halving_interval` blocks.
+pub fn block_subsidy_sats(height: u32, halving_interval: u32) -> u64 {
+    let halvings = height / halving_interval.max(1);
+    if halvings >= 64 {
+        return 0;
+    }
+    (50u64 * 100_000_000u64) >> halvings
+}
+
+/// Damping for stateful tier selection (`[policy.tier_switching]`).
+/// Both knobs at 0 reproduce per-sample selection.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TierSwitching {
+    /// Band around each tier threshold, in the same units as the threshold.
+    /// Moving up needs `threshold + hysteresis`, moving down needs `< threshold - hysteresis`.
+    #[serde(default)]
+    pub hysteresis: f64,
+
+    /// Minimum time in a tier before it may change again.
+    #[serde(default)]
+    pub min_dwell_secs: u64,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PolicyConfig {
     #[serde(default = "default_protocol_version")]
@@ -76,17 +324,128 @@ pub struct PolicyConfig {
     #[serde(default)]
     pub min_avg_fee_hi: u64,
 
+    /// Feerate floors in sat/vB per tier. 0 disables the check.
+    #[serde(default)]
+    pub min_feerate_lo: f64,
+    #[serde(default)]
+    pub min_feerate_mid: f64,
+    #[serde(default)]
+    pub min_feerate_hi: f64,
+
     #[serde(default = "default_reject_empty_templates")]
     pub reject_empty_templates: bool,
 
     #[serde(default = "default_reject_coinbase_zero")]
     pub reject_coinbase_zero: bool,
 
+    /// Reject templates not built on the current chain tip. No effect without a tip source.
+    #[serde(default = "default_reject_stale_tip")]
+    pub reject_stale_tip: bool,
+
+    /// Reject templates whose `created_at_unix_ms` is older than this on arrival. 0 disables.
+    #[serde(default)]
+    pub max_template_age_ms: u64,
+
+    /// How far `created_at_unix_ms` may lie in the verifier's future before it is flagged.
+    #[serde(default = "default_max_clock_skew_ms")]
+    pub max_clock_skew_ms: u64,
+
     #[serde(default = "default_unknown_mempool_as_high")]
     pub unknown_mempool_as_high: bool,
 
+    #[serde(default)]
+    pub evaluation_mode: EvaluationMode,
+
+    /// Ordered fee ladder. When empty, the legacy lo/mid/hi fields define three tiers.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tiers: Vec<FeeTierConfig>,
+
+    /// What tier thresholds (and the legacy low/high_mempool_tx) measure.
+    #[serde(default)]
+    pub tier_metric: TierMetric,
+
+    #[serde(default)]
+    pub tier_switching: TierSwitching,
+
+    #[serde(default)]
+    pub coinbase: CoinbaseCheck,
+
     #[serde(default)]
     pub safety: PolicySafety,
+
+    /// Per-rule overrides; rules not listed enforce.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub rule_modes: BTreeMap<String, RuleMode>,
+
+    /// Scheduled overrides (`[[policy.overlays]]`); the first active one wins.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub overlays: Vec<PolicyOverlay>,
+
+    /// Scripted checks (`[[policy.script_rules]]`), run after the built-in rules.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub script_rules: Vec<ScriptRule>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+/// One finding from `PolicyConfig::validation_report`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ValidationIssue {
+    /// Field path under `[policy]`, e.g. `tiers[2].threshold` or `safety.max_weight_ratio`.
+    pub path: String,
+    pub severity: Severity,
+    /// Stable machine code, e.g. `tiers.non_monotonic`.
+    pub code: String,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ValidationReport {
+    pub issues: Vec<ValidationIssue>,
+}
+
+impl ValidationReport {
+    fn push(
+        &mut self,
+        severity: Severity,
+        path: impl Into<String>,
+        code: &str,
+        message: impl Into<String>,
+    ) {
+        self.issues.push(ValidationIssue {
+            path: path.into(),
+            severity,
+            code: code.to_string(),
+            message: message.into(),
+        });
+    }
+
+    pub fn error(&mut self, path: impl Into<String>, code: &str, message: impl Into<String>) {
+        self.push(Severity::Error, path, code, message);
+    }
+
+    pub fn warning(&mut self, path: impl Into<String>, code: &str, message: impl Into<String>) {
+        self.push(Severity::Warning, path, code, message);
+    }
+
+    pub fn errors(&self) -> impl Iterator<Item = &ValidationIssue> {
+        self.issues.iter().filter(|i| i.severity == Severity::Error)
+    }
+
+    pub fn warnings(&self) -> impl Iterator<Item = &ValidationIssue> {
+        self.issues
+            .iter()
+            .filter(|i| i.severity == Severity::Warning)
+    }
+
+    pub fn has_errors(&self) -> bool {
+        self.errors().next().is_some()
+    }
 }
 
 fn default_protocol_version() -> u16 {
@@ -117,11 +476,19 @@ fn default_reject_coinbase_zero() -> bool {
     false
 }
 
+fn default_reject_stale_tip() -> bool {
+    true
+}
+
+fn default_max_clock_skew_ms() -> u64 {
+    2_000
+}
+
 fn default_unknown_mempool_as_high() -> bool {
     true
 }
 
-fn is_hex(s: &str) -> bool {
+pub(crate) fn is_hex(s: &str) -> bool {
     s.as_bytes().iter().all(|&b| b.is_ascii_hexdigit())
 }
 
@@ -137,158 +504,679 @@ impl PolicyConfig {
             min_avg_fee_lo: 0,
             min_avg_fee_mid: 500,
             min_avg_fee_hi: 2_000,
+            min_feerate_lo: 0.0,
+            min_feerate_mid: 0.0,
+            min_feerate_hi: 0.0,
             reject_empty_templates: true,
             reject_coinbase_zero: false,
+            reject_stale_tip: true,
+            max_template_age_ms: 0,
+            max_clock_skew_ms: 2_000,
             unknown_mempool_as_high: true,
+            evaluation_mode: EvaluationMode::FirstFailure,
+            tiers: Vec::new(),
+            tier_metric: TierMetric::TxCount,
+            tier_switching: TierSwitching::default(),
+            coinbase: CoinbaseCheck::default(),
             safety: PolicySafety {
                 max_weight_ratio: 0.999,
             },
+            rule_modes: BTreeMap::new(),
+            overlays: Vec::new(),
+            script_rules: Vec::new(),
         }
     }
 
+    /// Mode for the rule that reports `code`.
+    pub fn rule_mode(&self, code: &str) -> RuleMode {
+        self.rule_modes.get(code).copied().unwrap_or_default()
+    }
+
+    /// Fails with every error from `validation_report`, joined.
     pub fn validate(&self) -> anyhow::Result<()> {
-        use anyhow::anyhow;
+        let report = self.validation_report();
+        if report.has_errors() {
+            anyhow::bail!(
+                "{}",
+                report
+                    .errors()
+                    .map(|i| i.message.as_str())
+                    .collect::<Vec<_>>()
+                    .join("; ")
+            );
+        }
+        Ok(())
+    }
+
+    /// Every problem with this policy, not just the first. Errors make the policy
+    /// unusable; warnings flag settings that load but probably do not do what was meant.
+    pub fn validation_report(&self) -> ValidationReport {
+        self.validation_report_with_rules(&RuleRegistry::from_config(self))
+    }
+
+    /// `validation_report` for a policy evaluated by `rules`, so `[policy.rule_modes]`
+    /// may also name the configurable rules an embedder registered.
+    pub fn validation_report_with_rules(&self, rules: &RuleRegistry) -> ValidationReport {
+        let mut r = ValidationReport::default();
 
         if self.protocol_version != PROTOCOL_VERSION {
-            anyhow::bail!(
-                "policy.protocol_version={} does not match binary PROTOCOL_VERSION={}",
-                self.protocol_version,
-                PROTOCOL_VERSION
+            r.error(
+                "protocol_version",
+                "protocol_version.mismatch",
+                format!(
+                    "policy.protocol_version={} does not match binary PROTOCOL_VERSION={}",
+                    self.protocol_version, PROTOCOL_VERSION
+                ),
             );
         }
 
         if self.required_prevhash_len == 0 {
-            return Err(anyhow!("required_prevhash_len must be > 0"));
+            r.error(
+                "required_prevhash_len",
+                "required_prevhash_len.zero",
+                "required_prevhash_len must be > 0",
+            );
         }
 
         if self.max_tx_count == 0 {
-            return Err(anyhow!("max_tx_count must be > 0"));
+            r.error(
+                "max_tx_count",
+                "max_tx_count.zero",
+                "max_tx_count must be > 0",
+            );
         }
 
-        if self.low_mempool_tx > self.high_mempool_tx {
-            return Err(anyhow!(
-                "low_mempool_tx ({}) must be <= high_mempool_tx ({})",
-                self.low_mempool_tx,
-                self.high_mempool_tx
-            ));
+        if self.tiers.is_empty() {
+            if self.low_mempool_tx > self.high_mempool_tx {
+                r.error(
+                    "high_mempool_tx",
+                    "tiers.non_monotonic",
+                    format!(
+                        "low_mempool_tx ({}) must be <= high_mempool_tx ({})",
+                        self.low_mempool_tx, self.high_mempool_tx
+                    ),
+                );
+            }
+
+            for (name, v) in [
+                ("min_feerate_lo", self.min_feerate_lo),
+                ("min_feerate_mid", self.min_feerate_mid),
+                ("min_feerate_hi", self.min_feerate_hi),
+            ] {
+                if !(v.is_finite() && v >= 0.0) {
+                    r.error(
+                        name,
+                        "feerate.invalid",
+                        format!("{} ({}) must be a finite value >= 0", name, v),
+                    );
+                }
+            }
+        }
+
+        for (i, tier) in self.tiers.iter().enumerate() {
+            if tier.name.trim().is_empty() {
+                r.error(
+                    format!("tiers[{}].name", i),
+                    "tiers.name_empty",
+                    format!("tiers[{}].name must not be empty", i),
+                );
+            } else if self.tiers[..i].iter().any(|t| t.name == tier.name) {
+                r.error(
+                    format!("tiers[{}].name", i),
+                    "tiers.name_duplicate",
+                    format!("tiers[{}].name {:?} is duplicated", i, tier.name),
+                );
+            }
+            if !(tier.threshold.is_finite() && tier.threshold >= 0.0) {
+                r.error(
+                    format!("tiers[{}].threshold", i),
+                    "tiers.threshold_invalid",
+                    format!(
+                        "tiers[{}].threshold ({}) must be a finite value >= 0",
+                        i, tier.threshold
+                    ),
+                );
+            }
+            if !(tier.min_feerate.is_finite() && tier.min_feerate >= 0.0) {
+                r.error(
+                    format!("tiers[{}].min_feerate", i),
+                    "feerate.invalid",
+                    format!(
+                        "tiers[{}].min_feerate ({}) must be a finite value >= 0",
+                        i, tier.min_feerate
+                    ),
+                );
+            }
+            if i > 0 && tier.threshold < self.tiers[i - 1].threshold {
+                r.error(
+                    format!("tiers[{}].threshold", i),
+                    "tiers.non_monotonic",
+                    format!(
+                        "tiers[{}].threshold ({}) must be >= tiers[{}].threshold ({})",
+                        i,
+                        tier.threshold,
+                        i - 1,
+                        self.tiers[i - 1].threshold
+                    ),
+                );
+            }
+        }
+
+        if !(self.tier_switching.hysteresis.is_finite() && self.tier_switching.hysteresis >= 0.0) {
+            r.error(
+                "tier_switching.hysteresis",
+                "tier_switching.hysteresis_invalid",
+                format!(
+                    "tier_switching.hysteresis ({}) must be a finite value >= 0",
+                    self.tier_switching.hysteresis
+                ),
+            );
+        }
+
+        if self.coinbase.halving_interval == 0 {
+            r.error(
+                "coinbase.halving_interval",
+                "coinbase.halving_interval_zero",
+                "coinbase.halving_interval must be > 0",
+            );
         }
 
         if !(self.safety.max_weight_ratio > 0.0 && self.safety.max_weight_ratio <= 1.0) {
-            return Err(anyhow!(
-                "safety.max_weight_ratio ({}) must be in (0, 1]",
-                self.safety.max_weight_ratio
-            ));
+            r.error(
+                "safety.max_weight_ratio",
+                "safety.max_weight_ratio_out_of_range",
+                format!(
+                    "safety.max_weight_ratio ({}) must be in (0, 1]",
+                    self.safety.max_weight_ratio
+                ),
+            );
         }
 
-        Ok(())
+        // Built-ins behind a flag stay valid keys, so a policy can keep its modes
+        // while the flag is off; the loop warns that they do nothing then.
+        let configurable: Vec<&str> = CONFIGURABLE_RULES
+            .iter()
+            .copied()
+            .chain(rules.configurable_codes())
+            .chain(self.script_rules.iter().map(|s| s.reason_code.as_str()))
+            .collect();
+        for code in self.rule_modes.keys() {
+            if !configurable.contains(&code.as_str()) {
+                let hint = match crate::policy_lint::suggest(code, configurable.iter().copied()) {
+                    Some(s) => format!(" (did you mean {}?)", s),
+                    None => String::new(),
+                };
+                r.error(
+                    format!("rule_modes.{}", code),
+                    "rule_modes.unknown_rule",
+                    format!("rule_modes.{} is not a configurable rule{}", code, hint),
+                );
+            } else if self.rule_mode(code) != RuleMode::Off
+                && let Some(flag) = self.disabling_flag(code)
+            {
+                r.warning(
+                    format!("rule_modes.{}", code),
+                    "rule_modes.rule_disabled",
+                    format!(
+                        "rule_modes.{} has no effect: the rule is off because of {}",
+                        code, flag
+                    ),
+                );
+            }
+        }
+
+        self.script_rule_issues(&mut r);
+        self.overlay_issues(&mut r, rules);
+        self.ladder_warnings(&mut r);
+        r
     }
 
-    pub fn effective_min_avg_fee_dynamic(&self, mempool_tx: Option<u64>) -> (u64, FeeTier) {
-        match mempool_tx {
-            Some(tx) => {
-                if tx < self.low_mempool_tx {
-                    (self.min_avg_fee_lo, FeeTier::Low)
-                } else if tx < self.high_mempool_tx {
-                    (self.min_avg_fee_mid, FeeTier::Mid)
-                } else {
-                    (self.min_avg_fee_hi, FeeTier::High)
-                }
+    /// The setting that keeps the built-in rule reporting `code` out of
+    /// `RuleRegistry::from_config`, if it is currently doing so. Overlays cannot
+    /// change any of these.
+    fn disabling_flag(&self, code: &str) -> Option<&'static str> {
+        match code {
+            "stale_prev_hash" | "height_mismatch" if !self.reject_stale_tip => {
+                Some("reject_stale_tip = false")
             }
-            None => {
-                if self.unknown_mempool_as_high {
-                    (self.min_avg_fee_hi, FeeTier::High)
-                } else {
-                    (self.min_avg_fee_mid, FeeTier::Mid)
+            "template_too_old" if self.max_template_age_ms == 0 => Some("max_template_age_ms = 0"),
+            "empty_template_rejected" if !self.reject_empty_templates => {
+                Some("reject_empty_templates = false")
+            }
+            "coinbase_value_zero_rejected" if !self.reject_coinbase_zero => {
+                Some("reject_coinbase_zero = false")
+            }
+            "coinbase_value_overclaim" | "coinbase_value_underclaim" if !self.coinbase.enabled => {
+                Some("coinbase.enabled = false")
+            }
+            _ => None,
+        }
+    }
+
+    fn script_rule_issues(&self, r: &mut ValidationReport) {
+        let mut seen: Vec<&str> = Vec::new();
+
+        for (i, s) in self.script_rules.iter().enumerate() {
+            let at = |field: &str| format!("script_rules[{}].{}", i, field);
+            let name = s.name.trim();
+
+            if name.is_empty() {
+                r.error(
+                    at("name"),
+                    "script_rules.name_empty",
+                    format!("script_rules[{}].name must not be empty", i),
+                );
+            } else if seen.contains(&name) {
+                r.error(
+                    at("name"),
+                    "script_rules.name_duplicate",
+                    format!(
+                        "script_rules[{}].name '{}' is already used by an earlier script rule",
+                        i, name
+                    ),
+                );
+            } else {
+                seen.push(name);
+            }
+
+            let code = s.reason_code.as_str();
+            let well_formed = !code.is_empty()
+                && code
+                    .bytes()
+                    .all(|b| b.is_ascii_lowercase() || b.is_ascii_digit() || b == b'_');
+            if !well_formed {
+                r.error(
+                    at("reason_code"),
+                    "script_rules.reason_code_invalid",
+                    format!(
+                        "script_rules[{}].reason_code '{}' must be non-empty snake_case",
+                        i, code
+                    ),
+                );
+            } else if code == "ok" || rg_protocol::VerdictReason::BUILTIN_CODES.contains(&code) {
+                // Custom codes travel as bare strings, so a peer would read a
+                // built-in code as the real reason, and "ok" as an accept in stats.
+                r.error(
+                    at("reason_code"),
+                    "script_rules.reason_code_builtin",
+                    format!(
+                        "script_rules[{}].reason_code '{}' is a built-in reason code",
+                        i, code
+                    ),
+                );
+            }
+
+            if let Some(e) = s.compile_error() {
+                r.error(
+                    at("script"),
+                    "script_rules.compile_error",
+                    format!("script_rules[{}] ('{}') does not compile: {}", i, name, e),
+                );
+            }
+        }
+    }
+
+    /// Overlay schedules, plus every check above on the policy each overlay produces.
+    fn overlay_issues(&self, r: &mut ValidationReport, rules: &RuleRegistry) {
+        if self.overlays.is_empty() {
+            return;
+        }
+        let mut base = self.clone();
+        base.overlays.clear();
+        // Already reported against the base policy.
+        let base_issues = base.validation_report_with_rules(rules).issues;
+        let mut seen: Vec<&str> = Vec::new();
+
+        for (i, o) in self.overlays.iter().enumerate() {
+            let at = |field: &str| format!("overlays[{}].{}", i, field);
+            let name = o.name.trim();
+
+            if name.is_empty() {
+                r.error(
+                    at("name"),
+                    "overlays.name_empty",
+                    format!("overlays[{}].name must not be empty", i),
+                );
+            } else if seen.contains(&name) {
+                r.error(
+                    at("name"),
+                    "overlays.name_duplicate",
+                    format!(
+                        "overlays[{}].name '{}' is already used by an earlier overlay",
+                        i, name
+                    ),
+                );
+            } else {
+                seen.push(name);
+            }
+
+            if !o.has_time_window() && !o.has_height_range() {
+                r.error(
+                    format!("overlays[{}]", i),
+                    "overlays.no_schedule",
+                    format!(
+                        "overlays[{}] ('{}') sets neither a time window nor a height range",
+                        i, name
+                    ),
+                );
+            }
+
+            if let (Some(min), Some(max)) = (o.min_height, o.max_height)
+                && min > max
+            {
+                r.error(
+                    at("min_height"),
+                    "overlays.height_range_invalid",
+                    format!(
+                        "overlays[{}].min_height ({}) is above max_height ({})",
+                        i, min, max
+                    ),
+                );
+            }
+
+            if o.has_time_window() {
+                for (field, value) in [("from_utc", &o.from_utc), ("until_utc", &o.until_utc)] {
+                    match value {
+                        Some(v) if parse_hhmm(v).is_none() => r.error(
+                            at(field),
+                            "overlays.time_invalid",
+                            format!("overlays[{}].{} ('{}') must be HH:MM in UTC", i, field, v),
+                        ),
+                        Some(_) => {}
+                        None => r.error(
+                            at(field),
+                            "overlays.time_invalid",
+                            format!("overlays[{}] sets a time window without {}", i, field),
+                        ),
+                    }
+                }
+                if o.from_utc.is_some() && o.from_utc == o.until_utc {
+                    r.warning(
+                        at("until_utc"),
+                        "overlays.window_empty",
+                        format!(
+                            "overlays[{}] has from_utc equal to until_utc and is never active",
+                            i
+                        ),
+                    );
                 }
+            } else if !o.days.is_empty() {
+                r.error(
+                    at("days"),
+                    "overlays.days_without_window",
+                    format!("overlays[{}].days needs from_utc and until_utc", i),
+                );
+            }
+
+            for d in o.days.iter().filter(|d| !is_day_name(d)) {
+                r.error(
+                    at("days"),
+                    "overlays.day_invalid",
+                    format!("overlays[{}].days entry '{}' is not one of mon..sun", i, d),
+                );
+            }
+
+            let mut effective = base.clone();
+            o.apply(&mut effective);
+            for issue in effective
+                .validation_report_with_rules(rules)
+                .issues
+                .into_iter()
+                .filter(|issue| !base_issues.contains(issue))
+            {
+                r.push(
+                    issue.severity,
+                    format!("overlays[{}].{}", i, issue.path),
+                    &issue.code,
+                    format!("overlay '{}': {}", name, issue.message),
+                );
             }
         }
     }
-}
 
-pub fn evaluate(template: &TemplatePropose, cfg: &PolicyConfig) -> VerdictReason {
-    evaluate_dynamic(template, cfg, None).0
-}
+    /// Semantic checks on the fee ladder, legacy or explicit.
+    fn ladder_warnings(&self, r: &mut ValidationReport) {
+        let tiers = self.fee_tiers();
 
-pub fn evaluate_dynamic(
-    template: &TemplatePropose,
-    cfg: &PolicyConfig,
-    mempool_tx: Option<u64>,
-) -> (VerdictReason, FeeTier, u64) {
-    let (min_avg_fee_used, tier) = cfg.effective_min_avg_fee_dynamic(mempool_tx);
+        for i in 1..tiers.len() {
+            let (lower, upper) = (&tiers[i - 1], &tiers[i]);
+            if upper.min_avg_fee < lower.min_avg_fee {
+                r.warning(
+                    self.tier_field_path(i, "min_avg_fee"),
+                    "tiers.floor_decreasing",
+                    format!(
+                        "tier {:?} floor {} sats/tx is below tier {:?} floor {}; a busier mempool lowers the bar",
+                        upper.name, upper.min_avg_fee, lower.name, lower.min_avg_fee
+                    ),
+                );
+            }
+            if upper.min_feerate < lower.min_feerate {
+                r.warning(
+                    self.tier_field_path(i, "min_feerate"),
+                    "tiers.feerate_floor_decreasing",
+                    format!(
+                        "tier {:?} feerate floor {} sat/vB is below tier {:?} floor {}",
+                        upper.name, upper.min_feerate, lower.name, lower.min_feerate
+                    ),
+                );
+            }
+            // Selection takes the highest tier whose threshold is met, so a tier
+            // sharing its threshold with the next one never applies.
+            if upper.threshold == lower.threshold {
+                r.warning(
+                    self.tier_field_path(i - 1, "threshold"),
+                    "tiers.unreachable",
+                    format!(
+                        "tier {:?} has the same threshold ({}) as {:?} and is never selected",
+                        lower.name, lower.threshold, upper.name
+                    ),
+                );
+            }
+        }
+
+        // usage_ratio cannot exceed 1.0, so a higher threshold is only reachable
+        // through the unknown-mempool fallback.
+        if self.tier_metric == TierMetric::UsageRatio
+            && let Some(top) = tiers.last()
+            && tiers.len() > 1
+            && top.threshold > 1.0
+        {
+            let path = self.tier_field_path(tiers.len() - 1, "threshold");
+            if self.unknown_mempool_as_high {
+                r.warning(
+                    path,
+                    "tiers.unknown_mempool_only",
+                    format!(
+                        "tier {:?} threshold {} is above the usage_ratio maximum of 1.0; it only applies when the mempool is unknown",
+                        top.name, top.threshold
+                    ),
+                );
+            } else {
+                r.warning(
+                    "unknown_mempool_as_high",
+                    "tiers.unreachable",
+                    format!(
+                        "tier {:?} threshold {} is above the usage_ratio maximum of 1.0 and unknown_mempool_as_high is false, so it is never selected",
+                        top.name, top.threshold
+                    ),
+                );
+            }
+        }
+    }
 
-    if template.version != cfg.protocol_version {
-        return (
-            VerdictReason::ProtocolVersionMismatch {
-                got: template.version,
-                expected: cfg.protocol_version,
+    /// Field path for a ladder entry: `tiers[i].field`, or the legacy lo/mid/hi field.
+    fn tier_field_path(&self, index: usize, field: &str) -> String {
+        if !self.tiers.is_empty() {
+            return format!("tiers[{}].{}", index, field);
+        }
+        let suffix = ["lo", "mid", "hi"][index.min(2)];
+        match field {
+            "threshold" => match index {
+                0 | 1 => "low_mempool_tx".to_string(),
+                _ => "high_mempool_tx".to_string(),
             },
-            tier,
-            min_avg_fee_used,
-        );
+            _ => format!("{}_{}", field, suffix),
+        }
+    }
+
+    /// Weight ceiling in WU derived from `safety.max_weight_ratio`.
+    pub fn max_block_weight(&self) -> u64 {
+        (self.safety.max_weight_ratio * MAX_BLOCK_WEIGHT as f64).floor() as u64
     }
 
-    if template.prev_hash.len() != cfg.required_prevhash_len {
-        return (
-            VerdictReason::PrevHashLenMismatch {
-                len: template.prev_hash.len(),
-                expected: cfg.required_prevhash_len,
+    /// The active fee ladder, lowest tier first. Legacy policies map onto
+    /// low/mid/high using `low_mempool_tx` and `high_mempool_tx` as lower bounds.
+    pub fn fee_tiers(&self) -> Vec<FeeTierConfig> {
+        if !self.tiers.is_empty() {
+            return self.tiers.clone();
+        }
+
+        vec![
+            FeeTierConfig {
+                name: "low".to_string(),
+                threshold: 0.0,
+                min_avg_fee: self.min_avg_fee_lo,
+                min_feerate: self.min_feerate_lo,
             },
-            tier,
-            min_avg_fee_used,
-        );
+            FeeTierConfig {
+                name: "mid".to_string(),
+                threshold: self.low_mempool_tx as f64,
+                min_avg_fee: self.min_avg_fee_mid,
+                min_feerate: self.min_feerate_mid,
+            },
+            FeeTierConfig {
+                name: "high".to_string(),
+                threshold: self.high_mempool_tx as f64,
+                min_avg_fee: self.min_avg_fee_hi,
+                min_feerate: self.min_feerate_hi,
+            },
+        ]
     }
 
-    if !is_hex(&template.prev_hash) {
-        return (VerdictReason::InvalidPrevHash, tier, min_avg_fee_used);
+    /// The `tier_metric` reading for a snapshot; None means "mempool unknown".
+    pub fn tier_metric_value(&self, mempool: Option<&MempoolSnapshot>) -> Option<f64> {
+        mempool.and_then(|m| self.tier_metric.value(m))
     }
 
-    if cfg.reject_empty_templates && template.tx_count == 0 {
-        return (VerdictReason::EmptyTemplateRejected, tier, min_avg_fee_used);
+    /// Stateless tier pick for one metric sample. `offset` shifts every
+    /// threshold, which is how hysteresis bands are applied.
+    pub fn tier_index_for_sample(&self, metric: Option<f64>, offset: f64) -> usize {
+        let tiers = self.fee_tiers();
+
+        match metric {
+            // Highest tier whose lower bound the sample reaches; below the first bound, tier 0.
+            Some(value) => tiers
+                .iter()
+                .rposition(|t| value >= t.threshold + offset)
+                .unwrap_or(0),
+            // Unknown mempool: top tier, or the middle one (mid for lo/mid/hi).
+            None => {
+                if self.unknown_mempool_as_high {
+                    tiers.len() - 1
+                } else {
+                    (tiers.len() - 1) / 2
+                }
+            }
+        }
     }
 
-    if cfg.reject_coinbase_zero && template.coinbase_value == 0 && template.tx_count > 0 {
-        return (
-            VerdictReason::CoinbaseValueZeroRejected,
-            tier,
-            min_avg_fee_used,
-        );
+    /// Resolve a ladder index to a named tier, clamping to the top tier.
+    pub fn fee_tier_at(&self, index: usize) -> FeeTier {
+        let tiers = self.fee_tiers();
+        let index = index.min(tiers.len() - 1);
+        FeeTier {
+            index,
+            name: tiers[index].name.clone(),
+        }
     }
 
-    if template.tx_count > cfg.max_tx_count {
-        return (
-            VerdictReason::TxCountExceeded {
-                count: template.tx_count,
-                max_allowed: cfg.max_tx_count,
-            },
-            tier,
-            min_avg_fee_used,
-        );
+    pub fn effective_min_avg_fee_dynamic(
+        &self,
+        mempool: Option<&MempoolSnapshot>,
+    ) -> (u64, FeeTier) {
+        let metric = self.tier_metric_value(mempool);
+        let tier = self.fee_tier_at(self.tier_index_for_sample(metric, 0.0));
+        (self.min_avg_fee_for_tier(&tier), tier)
     }
 
-    if template.total_fees < cfg.min_total_fees {
-        return (
-            VerdictReason::TotalFeesBelowMinimum {
-                total: template.total_fees,
-                min_required: cfg.min_total_fees,
-            },
-            tier,
-            min_avg_fee_used,
-        );
+    pub fn min_avg_fee_for_tier(&self, tier: &FeeTier) -> u64 {
+        self.fee_tiers()
+            .get(tier.index)
+            .map(|t| t.min_avg_fee)
+            .unwrap_or(0)
     }
 
-    if min_avg_fee_used > 0 && template.tx_count > 0 {
-        let avg = template.total_fees / template.tx_count as u64;
-        if avg < min_avg_fee_used {
-            return (
-                VerdictReason::AvgFeeBelowMinimum {
-                    avg,
-                    min_required: min_avg_fee_used,
-                },
-                tier,
-                min_avg_fee_used,
-            );
-        }
+    pub fn min_feerate_for_tier(&self, tier: &FeeTier) -> f64 {
+        self.fee_tiers()
+            .get(tier.index)
+            .map(|t| t.min_feerate)
+            .unwrap_or(0.0)
+    }
+}
+
+/// Template feerate in sat/vB, or None when the sender did not report vsize.
+pub fn template_feerate_sat_vb(template: &TemplatePropose) -> Option<f64> {
+    match template.total_vsize {
+        Some(vsize) if vsize > 0 => Some(template.total_fees as f64 / vsize as f64),
+        _ => None,
     }
+}
+
+/// Per-template facts from outside the template and policy. Fields left as
+/// None skip the rules that need them.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct EvalContext<'a> {
+    /// Current best block; drives the stale-tip checks.
+    pub chain_tip: Option<&'a ChainTip>,
+    /// When the verifier received the template; drives the template age checks.
+    pub received_at_unix_ms: Option<u64>,
+    /// Mempool sample the tier was picked from; exposed to script rules.
+    pub mempool: Option<&'a MempoolSnapshot>,
+}
+
+pub fn evaluate(template: &TemplatePropose, cfg: &PolicyConfig) -> VerdictReason {
+    evaluate_dynamic(template, cfg, None).0
+}
 
-    (VerdictReason::Ok, tier, min_avg_fee_used)
+pub fn evaluate_dynamic(
+    template: &TemplatePropose,
+    cfg: &PolicyConfig,
+    mempool: Option<&MempoolSnapshot>,
+) -> (VerdictReason, FeeTier, u64) {
+    let (_, tier) = cfg.effective_min_avg_fee_dynamic(mempool);
+    let (violations, _, tier, min_avg_fee_used) = RuleRegistry::from_config(cfg)
+        .evaluate_with_mode(
+            template,
+            cfg,
+            tier,
+            EvalContext::default(),
+            EvaluationMode::FirstFailure,
+        );
+    let reason = violations.into_iter().next().unwrap_or(VerdictReason::Ok);
+    (reason, tier, min_avg_fee_used)
+}
+
+/// Evaluate honoring `cfg.evaluation_mode` and `cfg.rule_modes`. Violations come
+/// back in rule order, so the first entry is the primary reason; an empty list
+/// means accepted. The second list holds failures of rules set to `warn`.
+pub fn evaluate_violations(
+    template: &TemplatePropose,
+    cfg: &PolicyConfig,
+    mempool: Option<&MempoolSnapshot>,
+    ctx: EvalContext<'_>,
+) -> (Vec<VerdictReason>, Vec<VerdictReason>, FeeTier, u64) {
+    let (_, tier) = cfg.effective_min_avg_fee_dynamic(mempool);
+    evaluate_violations_for_tier(template, cfg, tier, ctx)
+}
+
+/// Like `evaluate_violations`, with the tier already chosen (e.g. by a `TierState`).
+/// Builds the registry from `cfg` on every call; callers evaluating many templates
+/// keep one `RuleRegistry` per policy and call `RuleRegistry::evaluate`.
+pub fn evaluate_violations_for_tier(
+    template: &TemplatePropose,
+    cfg: &PolicyConfig,
+    tier: FeeTier,
+    ctx: EvalContext<'_>,
+) -> (Vec<VerdictReason>, Vec<VerdictReason>, FeeTier, u64) {
+    RuleRegistry::from_config(cfg).evaluate(template, cfg, tier, ctx)
 }
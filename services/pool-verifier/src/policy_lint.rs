@@ -0,0 +1,204 @@
+//! Key-level checks on a `[policy]` table that serde's `#[serde(default)]`
+//! fields would otherwise hide: misspelled or unknown keys, and fields left at
+//! their defaults.
+
+use rg_protocol::PROTOCOL_VERSION;
+use serde::Serialize;
+use toml::Value;
+use toml::value::Table;
+
+use crate::policy::{FeeTierConfig, PolicyConfig};
+use crate::schedule::PolicyOverlay;
+use crate::script_rules::ScriptRule;
+
+/// Arrays of tables whose element keys are checked against this sample,
+/// since an empty default array carries no keys to compare with.
+fn array_table_samples() -> Vec<(&'static str, Table)> {
+    let tier = FeeTierConfig {
+        name: String::new(),
+        threshold: 0.0,
+        min_avg_fee: 0,
+        min_feerate: 0.0,
+    };
+    vec![
+        ("tiers", to_table(&tier)),
+        ("overlays", to_table(&PolicyOverlay::key_sample())),
+        ("script_rules", to_table(&ScriptRule::key_sample())),
+    ]
+}
+
+/// Arrays of sparse overrides: keys an entry leaves out are not defaults.
+const OVERRIDE_ARRAY_TABLES: &[&str] = &["overlays", "script_rules"];
+
+/// Fields `PolicyConfig::fee_tiers` only reads when `tiers` is empty.
+const LEGACY_TIER_FIELDS: &[&str] = &[
+    "low_mempool_tx",
+    "high_mempool_tx",
+    "min_avg_fee_lo",
+    "min_avg_fee_mid",
+    "min_avg_fee_hi",
+    "min_feerate_lo",
+    "min_feerate_mid",
+    "min_feerate_hi",
+];
+
+/// Tables whose keys are free-form (checked by `PolicyConfig::validation_report`).
+const MAP_TABLES: &[&str] = &["rule_modes"];
+
+fn to_table<T: Serialize>(v: &T) -> Table {
+    match Value::try_from(v) {
+        Ok(Value::Table(t)) => t,
+        _ => Table::new(),
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct UnknownKey {
+    /// Dotted path under `[policy]`, e.g. `safety.max_weigth_ratio` or `tiers[1].min_fee`.
+    pub path: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub suggestion: Option<String>,
+}
+
+impl std::fmt::Display for UnknownKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.suggestion {
+            Some(s) => write!(f, "unknown key policy.{} (did you mean {}?)", self.path, s),
+            None => write!(f, "unknown key policy.{}", self.path),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct PolicyLint {
+    pub unknown_keys: Vec<UnknownKey>,
+    /// Known fields the table never set, so they took their defaults.
+    pub defaulted_fields: Vec<String>,
+}
+
+impl PolicyLint {
+    pub fn is_clean(&self) -> bool {
+        self.unknown_keys.is_empty()
+    }
+
+    pub fn warnings(&self) -> Vec<String> {
+        self.unknown_keys.iter().map(|k| k.to_string()).collect()
+    }
+}
+
+/// Compare a `[policy]` table against the keys `PolicyConfig` understands.
+pub fn lint_policy_table(policy: &Value) -> PolicyLint {
+    let known = to_table(&PolicyConfig::default_with_protocol(PROTOCOL_VERSION));
+    let mut lint = PolicyLint::default();
+
+    let Some(given) = policy.as_table() else {
+        return lint;
+    };
+    lint_table(given, &known, "", &mut lint);
+
+    for (name, sample) in array_table_samples() {
+        match given.get(name) {
+            Some(Value::Array(items)) => {
+                let overrides = OVERRIDE_ARRAY_TABLES.contains(&name);
+                for (i, item) in items.iter().enumerate() {
+                    if let Some(t) = item.as_table() {
+                        let mut item_lint = PolicyLint::default();
+                        lint_table(t, &sample, &format!("{}[{}].", name, i), &mut item_lint);
+                        lint.unknown_keys.extend(item_lint.unknown_keys);
+                        if !overrides {
+                            lint.defaulted_fields.extend(item_lint.defaulted_fields);
+                        }
+                    }
+                }
+            }
+            Some(_) => {}
+            None if OVERRIDE_ARRAY_TABLES.contains(&name) => {}
+            None => lint.defaulted_fields.push(name.to_string()),
+        }
+    }
+
+    // An explicit ladder replaces the legacy lo/mid/hi fields, so leaving them
+    // unset is not worth reporting.
+    if given.contains_key("tiers") {
+        lint.defaulted_fields
+            .retain(|f| !LEGACY_TIER_FIELDS.contains(&f.as_str()));
+    }
+    lint.defaulted_fields.sort();
+
+    lint
+}
+
+/// Lint the `[policy]` table of a whole policy file. Text that is not valid
+/// TOML yields an empty lint; parsing reports that error separately.
+pub fn lint_policy_text(text: &str) -> PolicyLint {
+    match toml::from_str::<Value>(text) {
+        Ok(v) => v.get("policy").map(lint_policy_table).unwrap_or_default(),
+        Err(_) => PolicyLint::default(),
+    }
+}
+
+fn lint_table(given: &Table, known: &Table, prefix: &str, lint: &mut PolicyLint) {
+    let is_array_table =
+        |k: &str| prefix.is_empty() && array_table_samples().iter().any(|(n, _)| *n == k);
+    let is_map_table = |k: &str| MAP_TABLES.contains(&k);
+
+    for (key, value) in given {
+        if is_array_table(key) || is_map_table(key) {
+            continue;
+        }
+        match known.get(key) {
+            Some(Value::Table(sub_known)) => {
+                if let Value::Table(sub_given) = value {
+                    lint_table(sub_given, sub_known, &format!("{}{}.", prefix, key), lint);
+                }
+            }
+            Some(_) => {}
+            None => lint.unknown_keys.push(UnknownKey {
+                path: format!("{}{}", prefix, key),
+                suggestion: suggest(key, known.keys().map(String::as_str))
+                    .map(|s| format!("{}{}", prefix, s)),
+            }),
+        }
+    }
+
+    for (key, value) in known {
+        if given.contains_key(key) {
+            continue;
+        }
+        match value {
+            Value::Table(sub_known) => lint_table(
+                &Table::new(),
+                sub_known,
+                &format!("{}{}.", prefix, key),
+                lint,
+            ),
+            _ => lint.defaulted_fields.push(format!("{}{}", prefix, key)),
+        }
+    }
+}
+
+/// Closest known key within a small edit distance, if any.
+pub(crate) fn suggest<'a>(key: &str, candidates: impl Iterator<Item = &'a str>) -> Option<String> {
+    let max_distance = (key.len() / 3).clamp(1, 3);
+    candidates
+        .map(|c| (edit_distance(key, c), c))
+        .filter(|(d, _)| *d <= max_distance)
+        .min_by_key(|(d, _)| *d)
+        .map(|(_, c)| c.to_string())
+}
+
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+    let mut cur = vec![0; b.len() + 1];
+
+    for (i, ca) in a.chars().enumerate() {
+        cur[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let sub = prev[j] + usize::from(ca != *cb);
+            cur[j + 1] = sub.min(prev[j + 1] + 1).min(cur[j] + 1);
+        }
+        std::mem::swap(&mut prev, &mut cur);
+    }
+    prev[b.len()]
+}
@@ -0,0 +1,76 @@
+use std::time::{Duration, SystemTime};
+
+use tokio::signal::unix::{Signal, SignalKind, signal};
+
+use crate::state::AppState;
+
+/// How often `VELDRA_POLICY_FILE` is checked for changes. 0 disables polling
+/// (SIGHUP still reloads).
+pub fn policy_watch_interval_from_env() -> Option<Duration> {
+    let secs = std::env::var("VELDRA_POLICY_WATCH_SECS")
+        .ok()
+        .and_then(|s| s.parse::<u64>().ok())
+        .unwrap_or(2);
+    (secs > 0).then(|| Duration::from_secs(secs))
+}
+
+fn file_stamp(path: &str) -> Option<(SystemTime, u64)> {
+    let meta = std::fs::metadata(path).ok()?;
+    Some((meta.modified().ok()?, meta.len()))
+}
+
+fn reload(state: &AppState, trigger: &str) {
+    match state.reload_policy_file() {
+        Ok(Some(version)) => println!(
+            "[policy] reloaded {} on {} (version {})",
+            state.policy_path, trigger, version
+        ),
+        Ok(None) => {}
+        Err(e) => eprintln!(
+            "[policy] reload on {} rejected, keeping active policy: {e:#}",
+            trigger
+        ),
+    }
+}
+
+async fn next_hangup(hup: &mut Option<Signal>) -> Option<()> {
+    match hup.as_mut() {
+        Some(h) => h.recv().await,
+        None => std::future::pending().await,
+    }
+}
+
+/// Reload the policy file whenever its mtime or size changes, and on SIGHUP.
+pub async fn run_policy_watcher(state: AppState, interval: Option<Duration>) {
+    let mut hup = match signal(SignalKind::hangup()) {
+        Ok(s) => Some(s),
+        Err(e) => {
+            eprintln!("[policy] SIGHUP handler install failed: {e:?}");
+            None
+        }
+    };
+
+    let mut last_stamp = file_stamp(&state.policy_path);
+    let mut ticker = tokio::time::interval(interval.unwrap_or(Duration::from_secs(3600)));
+    ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+    loop {
+        tokio::select! {
+            _ = ticker.tick(), if interval.is_some() => {
+                let stamp = file_stamp(&state.policy_path);
+                if stamp.is_some() && stamp != last_stamp {
+                    last_stamp = stamp;
+                    reload(&state, "file change");
+                }
+            }
+            sig = next_hangup(&mut hup) => {
+                if sig.is_none() {
+                    hup = None;
+                    continue;
+                }
+                last_stamp = file_stamp(&state.policy_path);
+                reload(&state, "SIGHUP");
+            }
+        }
+    }
+}
@@ -0,0 +1,100 @@
+use std::path::PathBuf;
+
+use rg_protocol::TemplatePropose;
+use serde::{Deserialize, Serialize};
+
+use crate::chain_tip::ChainTip;
+use crate::mempool::MempoolSnapshot;
+use crate::policy::{EvalContext, PolicyConfig, VerdictReason};
+use crate::rules::RuleRegistry;
+use crate::tier_state::TierState;
+
+/// Everything the verifier looked at for one proposal. Stored on each logged
+/// verdict so a candidate policy can be replayed against the same inputs.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VerdictInput {
+    pub template: TemplatePropose,
+
+    /// Mempool sample used for tier selection; None when it was unavailable.
+    #[serde(default)]
+    pub mempool: Option<MempoolSnapshot>,
+
+    /// Chain tip the template was checked against; None when no fresh tip was known.
+    #[serde(default)]
+    pub chain_tip: Option<ChainTip>,
+
+    pub received_at_unix_ms: u64,
+
+    /// Clock value handed to `TierState::select` (dwell timing).
+    pub evaluated_at_unix_ms: u64,
+}
+
+/// Outcome of one proposal under some policy.
+#[derive(Debug, Clone)]
+pub struct ReplayOutcome {
+    /// Every failed rule, primary first. Empty means accepted.
+    pub reasons: Vec<VerdictReason>,
+    pub fee_tier: String,
+    /// Overlay the policy had active for this proposal.
+    pub overlay: Option<String>,
+}
+
+impl ReplayOutcome {
+    pub fn accepted(&self) -> bool {
+        self.reasons.is_empty()
+    }
+
+    pub fn primary_code(&self) -> &str {
+        self.reasons.first().map(|r| r.code()).unwrap_or("ok")
+    }
+}
+
+/// Replays recorded inputs through a policy. Feed inputs in log order: tier
+/// selection is stateful (hysteresis, dwell) just like the live verifier.
+pub struct Replayer {
+    cfg: PolicyConfig,
+    rules: RuleRegistry,
+    tiers: TierState,
+}
+
+impl Replayer {
+    pub fn new(cfg: PolicyConfig) -> Self {
+        Self {
+            rules: RuleRegistry::from_config(&cfg),
+            cfg,
+            tiers: TierState::default(),
+        }
+    }
+
+    pub fn evaluate(&mut self, input: &VerdictInput) -> ReplayOutcome {
+        let (cfg, overlay) = self
+            .cfg
+            .clone()
+            .with_active_overlay(input.template.block_height, input.evaluated_at_unix_ms);
+        let tier = self
+            .tiers
+            .select(&cfg, input.mempool.as_ref(), input.evaluated_at_unix_ms);
+        let ctx = EvalContext {
+            chain_tip: input.chain_tip.as_ref(),
+            received_at_unix_ms: Some(input.received_at_unix_ms),
+            mempool: input.mempool.as_ref(),
+        };
+        let (reasons, _, tier, _) = self.rules.evaluate(&input.template, &cfg, tier, ctx);
+
+        ReplayOutcome {
+            reasons,
+            fee_tier: tier.as_str().to_string(),
+            overlay,
+        }
+    }
+}
+
+/// `path` plus its rotations (`path.N` .. `path.1`), oldest first, skipping missing files.
+pub fn verdict_log_files(path: &str, rotations: usize) -> Vec<PathBuf> {
+    (1..=rotations)
+        .rev()
+        .map(|i| PathBuf::from(format!("{}.{}", path, i)))
+        .chain(std::iter::once(PathBuf::from(path)))
+        .filter(|p| p.exists())
+        .collect()
+}
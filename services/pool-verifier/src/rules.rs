@@ -0,0 +1,637 @@
+//! Template checks as a list of `PolicyRule`s. `RuleRegistry::from_config`
+//! builds the built-in list for a policy; embedders can `register` their own
+//! rules after it. A registry is built once per policy, not per template.
+
+use rg_protocol::TemplatePropose;
+
+use crate::policy::{
+    EvalContext, EvaluationMode, FeeTier, PolicyConfig, RuleMode, VerdictReason,
+    block_subsidy_sats, is_hex, template_feerate_sat_vb,
+};
+
+/// Everything a rule may look at for one template.
+#[derive(Debug, Clone, Copy)]
+pub struct RuleInput<'a> {
+    pub template: &'a TemplatePropose,
+    pub cfg: &'a PolicyConfig,
+    pub tier: &'a FeeTier,
+    /// Average fee floor (sats/tx) of `tier`.
+    pub min_avg_fee: u64,
+    /// Feerate floor (sat/vB) of `tier`.
+    pub min_feerate: f64,
+    pub ctx: EvalContext<'a>,
+}
+
+#[derive(Debug, Clone)]
+pub enum RuleOutcome {
+    Pass,
+    Fail(VerdictReason),
+}
+
+pub trait PolicyRule: Send + Sync {
+    /// Short identifier for logs.
+    fn name(&self) -> &str;
+
+    /// Code reported when the rule fails; also its key in `[policy.rule_modes]`.
+    fn reason_code(&self) -> &str;
+
+    /// Whether `[policy.rule_modes]` may set this rule to warn or off.
+    fn configurable(&self) -> bool {
+        true
+    }
+
+    fn evaluate(&self, input: &RuleInput<'_>) -> RuleOutcome;
+}
+
+/// Ordered rule list. Order matters: in first-failure mode the first failing
+/// rule is the verdict's primary reason.
+#[derive(Default)]
+pub struct RuleRegistry {
+    rules: Vec<Box<dyn PolicyRule>>,
+}
+
+impl std::fmt::Debug for RuleRegistry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_list()
+            .entries(self.rules().map(|r| r.name()))
+            .finish()
+    }
+}
+
+impl RuleRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The built-in checks `cfg` turns on, in verdict priority order. Limits an
+    /// overlay can change are read from `RuleInput::cfg` when the rule runs, so
+    /// the registry also serves `cfg` with any of its overlays applied.
+    pub fn from_config(cfg: &PolicyConfig) -> Self {
+        let mut r = Self::new();
+
+        r.register(ProtocolVersionRule {
+            expected: cfg.protocol_version,
+        });
+        r.register(PrevHashLenRule {
+            expected: cfg.required_prevhash_len,
+        });
+        r.register(PrevHashHexRule);
+
+        if cfg.reject_stale_tip {
+            r.register(StaleTipRule);
+            r.register(TipHeightRule);
+        }
+
+        r.register(ClockSkewRule {
+            max_skew_ms: cfg.max_clock_skew_ms,
+        });
+        if cfg.max_template_age_ms > 0 {
+            r.register(TemplateAgeRule {
+                max_age_ms: cfg.max_template_age_ms,
+            });
+        }
+
+        if cfg.reject_empty_templates {
+            r.register(EmptyTemplateRule);
+        }
+        if cfg.reject_coinbase_zero {
+            r.register(CoinbaseZeroRule);
+        }
+        if cfg.coinbase.enabled {
+            r.register(CoinbaseOverclaimRule {
+                halving_interval: cfg.coinbase.halving_interval,
+            });
+            r.register(CoinbaseUnderclaimRule {
+                halving_interval: cfg.coinbase.halving_interval,
+                tolerance: cfg.coinbase.underclaim_tolerance_sats,
+            });
+        }
+
+        r.register(MaxTxCountRule);
+        r.register(MaxWeightRule {
+            max_allowed: cfg.max_block_weight(),
+        });
+        r.register(MinTotalFeesRule);
+        r.register(MinAvgFeeRule);
+        r.register(MinFeerateRule);
+
+        for script in &cfg.script_rules {
+            r.register(script.clone());
+        }
+
+        r
+    }
+
+    /// Append a rule; it runs after every rule already registered.
+    pub fn register(&mut self, rule: impl PolicyRule + 'static) -> &mut Self {
+        self.rules.push(Box::new(rule));
+        self
+    }
+
+    pub fn rules(&self) -> impl Iterator<Item = &dyn PolicyRule> {
+        self.rules.iter().map(|r| r.as_ref())
+    }
+
+    /// Reason codes `[policy.rule_modes]` may name for these rules.
+    pub fn configurable_codes(&self) -> impl Iterator<Item = &str> {
+        self.rules()
+            .filter(|r| r.configurable())
+            .map(|r| r.reason_code())
+    }
+
+    /// Run the rules honoring `cfg.evaluation_mode` and `cfg.rule_modes`.
+    /// Returns (violations, warnings, tier, min_avg_fee_used) like
+    /// `policy::evaluate_violations_for_tier`.
+    pub fn evaluate(
+        &self,
+        template: &TemplatePropose,
+        cfg: &PolicyConfig,
+        tier: FeeTier,
+        ctx: EvalContext<'_>,
+    ) -> (Vec<VerdictReason>, Vec<VerdictReason>, FeeTier, u64) {
+        self.evaluate_with_mode(template, cfg, tier, ctx, cfg.evaluation_mode)
+    }
+
+    pub fn evaluate_with_mode(
+        &self,
+        template: &TemplatePropose,
+        cfg: &PolicyConfig,
+        tier: FeeTier,
+        ctx: EvalContext<'_>,
+        mode: EvaluationMode,
+    ) -> (Vec<VerdictReason>, Vec<VerdictReason>, FeeTier, u64) {
+        let min_avg_fee_used = cfg.min_avg_fee_for_tier(&tier);
+        let mut violations = Vec::new();
+        let mut warnings = Vec::new();
+
+        let input = RuleInput {
+            template,
+            cfg,
+            tier: &tier,
+            min_avg_fee: min_avg_fee_used,
+            min_feerate: cfg.min_feerate_for_tier(&tier),
+            ctx,
+        };
+
+        for rule in &self.rules {
+            let rule_mode = if rule.configurable() {
+                cfg.rule_mode(rule.reason_code())
+            } else {
+                RuleMode::Enforce
+            };
+            if rule_mode == RuleMode::Off {
+                continue;
+            }
+
+            let RuleOutcome::Fail(reason) = rule.evaluate(&input) else {
+                continue;
+            };
+            if rule_mode == RuleMode::Warn {
+                warnings.push(reason);
+                continue;
+            }
+            violations.push(reason);
+            if mode == EvaluationMode::FirstFailure {
+                break;
+            }
+        }
+
+        (violations, warnings, tier, min_avg_fee_used)
+    }
+}
+
+fn fail_if(failed: bool, reason: impl FnOnce() -> VerdictReason) -> RuleOutcome {
+    if failed {
+        RuleOutcome::Fail(reason())
+    } else {
+        RuleOutcome::Pass
+    }
+}
+
+struct ProtocolVersionRule {
+    expected: u16,
+}
+
+impl PolicyRule for ProtocolVersionRule {
+    fn name(&self) -> &str {
+        "protocol_version"
+    }
+
+    fn reason_code(&self) -> &str {
+        "protocol_version_mismatch"
+    }
+
+    fn configurable(&self) -> bool {
+        false
+    }
+
+    fn evaluate(&self, input: &RuleInput<'_>) -> RuleOutcome {
+        let got = input.template.version;
+        fail_if(got != self.expected, || {
+            VerdictReason::ProtocolVersionMismatch {
+                got,
+                expected: self.expected,
+            }
+        })
+    }
+}
+
+struct PrevHashLenRule {
+    expected: usize,
+}
+
+impl PolicyRule for PrevHashLenRule {
+    fn name(&self) -> &str {
+        "prev_hash_len"
+    }
+
+    fn reason_code(&self) -> &str {
+        "prev_hash_len_mismatch"
+    }
+
+    fn configurable(&self) -> bool {
+        false
+    }
+
+    fn evaluate(&self, input: &RuleInput<'_>) -> RuleOutcome {
+        let len = input.template.prev_hash.len();
+        fail_if(len != self.expected, || {
+            VerdictReason::PrevHashLenMismatch {
+                len,
+                expected: self.expected,
+            }
+        })
+    }
+}
+
+struct PrevHashHexRule;
+
+impl PolicyRule for PrevHashHexRule {
+    fn name(&self) -> &str {
+        "prev_hash_hex"
+    }
+
+    fn reason_code(&self) -> &str {
+        "invalid_prev_hash"
+    }
+
+    fn configurable(&self) -> bool {
+        false
+    }
+
+    fn evaluate(&self, input: &RuleInput<'_>) -> RuleOutcome {
+        fail_if(!is_hex(&input.template.prev_hash), || {
+            VerdictReason::InvalidPrevHash
+        })
+    }
+}
+
+/// Only runs when the verifier knows the chain tip.
+struct StaleTipRule;
+
+impl PolicyRule for StaleTipRule {
+    fn name(&self) -> &str {
+        "stale_tip"
+    }
+
+    fn reason_code(&self) -> &str {
+        "stale_prev_hash"
+    }
+
+    fn evaluate(&self, input: &RuleInput<'_>) -> RuleOutcome {
+        let Some(tip) = input.ctx.chain_tip else {
+            return RuleOutcome::Pass;
+        };
+        fail_if(!tip.is_parent_of(&input.template.prev_hash), || {
+            VerdictReason::StalePrevHash {
+                got: input.template.prev_hash.clone(),
+                expected: tip.hash.clone(),
+            }
+        })
+    }
+}
+
+/// Only runs when the verifier knows the chain tip.
+struct TipHeightRule;
+
+impl PolicyRule for TipHeightRule {
+    fn name(&self) -> &str {
+        "tip_height"
+    }
+
+    fn reason_code(&self) -> &str {
+        "height_mismatch"
+    }
+
+    fn evaluate(&self, input: &RuleInput<'_>) -> RuleOutcome {
+        let Some(tip) = input.ctx.chain_tip else {
+            return RuleOutcome::Pass;
+        };
+        let expected = tip.height.saturating_add(1);
+        let height = input.template.block_height;
+        fail_if(height != expected, || VerdictReason::HeightMismatch {
+            height,
+            expected,
+        })
+    }
+}
+
+/// Senders that predate created_at_unix_ms are not penalized.
+fn created_and_received(input: &RuleInput<'_>) -> Option<(u64, u64)> {
+    Some((
+        input.template.created_at_unix_ms?,
+        input.ctx.received_at_unix_ms?,
+    ))
+}
+
+struct ClockSkewRule {
+    max_skew_ms: u64,
+}
+
+impl PolicyRule for ClockSkewRule {
+    fn name(&self) -> &str {
+        "clock_skew"
+    }
+
+    fn reason_code(&self) -> &str {
+        "template_timestamp_in_future"
+    }
+
+    fn evaluate(&self, input: &RuleInput<'_>) -> RuleOutcome {
+        let Some((created, received)) = created_and_received(input) else {
+            return RuleOutcome::Pass;
+        };
+        let ahead_ms = created.saturating_sub(received);
+        fail_if(ahead_ms > self.max_skew_ms, || {
+            VerdictReason::TemplateTimestampInFuture {
+                ahead_ms,
+                max_skew: self.max_skew_ms,
+            }
+        })
+    }
+}
+
+struct TemplateAgeRule {
+    max_age_ms: u64,
+}
+
+impl PolicyRule for TemplateAgeRule {
+    fn name(&self) -> &str {
+        "template_age"
+    }
+
+    fn reason_code(&self) -> &str {
+        "template_too_old"
+    }
+
+    fn evaluate(&self, input: &RuleInput<'_>) -> RuleOutcome {
+        let Some((created, received)) = created_and_received(input) else {
+            return RuleOutcome::Pass;
+        };
+        let age_ms = received.saturating_sub(created);
+        fail_if(age_ms > self.max_age_ms, || VerdictReason::TemplateTooOld {
+            age_ms,
+            max_allowed: self.max_age_ms,
+        })
+    }
+}
+
+struct EmptyTemplateRule;
+
+impl PolicyRule for EmptyTemplateRule {
+    fn name(&self) -> &str {
+        "empty_template"
+    }
+
+    fn reason_code(&self) -> &str {
+        "empty_template_rejected"
+    }
+
+    fn evaluate(&self, input: &RuleInput<'_>) -> RuleOutcome {
+        fail_if(input.template.tx_count == 0, || {
+            VerdictReason::EmptyTemplateRejected
+        })
+    }
+}
+
+struct CoinbaseZeroRule;
+
+impl PolicyRule for CoinbaseZeroRule {
+    fn name(&self) -> &str {
+        "coinbase_zero"
+    }
+
+    fn reason_code(&self) -> &str {
+        "coinbase_value_zero_rejected"
+    }
+
+    fn evaluate(&self, input: &RuleInput<'_>) -> RuleOutcome {
+        let t = input.template;
+        // A substituted fallback means the backend itself reported zero.
+        fail_if(
+            (t.coinbase_value == 0 || t.coinbase_value_substituted) && t.tx_count > 0,
+            || VerdictReason::CoinbaseValueZeroRejected {
+                substituted: t.coinbase_value_substituted,
+            },
+        )
+    }
+}
+
+/// Block subsidy + fees.
+fn expected_coinbase(input: &RuleInput<'_>, halving_interval: u32) -> u64 {
+    let t = input.template;
+    block_subsidy_sats(t.block_height, halving_interval).saturating_add(t.total_fees)
+}
+
+/// What the backend claimed. A substitute was computed by the sender, so the
+/// backend's own claim was 0.
+fn claimed_coinbase(input: &RuleInput<'_>) -> u64 {
+    let t = input.template;
+    if t.coinbase_value_substituted {
+        0
+    } else {
+        t.coinbase_value
+    }
+}
+
+struct CoinbaseOverclaimRule {
+    halving_interval: u32,
+}
+
+impl PolicyRule for CoinbaseOverclaimRule {
+    fn name(&self) -> &str {
+        "coinbase_overclaim"
+    }
+
+    fn reason_code(&self) -> &str {
+        "coinbase_value_overclaim"
+    }
+
+    fn evaluate(&self, input: &RuleInput<'_>) -> RuleOutcome {
+        let expected = expected_coinbase(input, self.halving_interval);
+        let coinbase_value = claimed_coinbase(input);
+        fail_if(coinbase_value > expected, || {
+            VerdictReason::CoinbaseValueOverclaim {
+                coinbase_value,
+                expected,
+            }
+        })
+    }
+}
+
+struct CoinbaseUnderclaimRule {
+    halving_interval: u32,
+    tolerance: u64,
+}
+
+impl PolicyRule for CoinbaseUnderclaimRule {
+    fn name(&self) -> &str {
+        "coinbase_underclaim"
+    }
+
+    fn reason_code(&self) -> &str {
+        "coinbase_value_underclaim"
+    }
+
+    fn evaluate(&self, input: &RuleInput<'_>) -> RuleOutcome {
+        let expected = expected_coinbase(input, self.halving_interval);
+        let coinbase_value = claimed_coinbase(input);
+        fail_if(
+            coinbase_value < expected.saturating_sub(self.tolerance),
+            || VerdictReason::CoinbaseValueUnderclaim {
+                coinbase_value,
+                expected,
+                tolerance: self.tolerance,
+                substituted: input.template.coinbase_value_substituted,
+            },
+        )
+    }
+}
+
+/// Overlays can change the limit, so it is read per template.
+struct MaxTxCountRule;
+
+impl PolicyRule for MaxTxCountRule {
+    fn name(&self) -> &str {
+        "max_tx_count"
+    }
+
+    fn reason_code(&self) -> &str {
+        "tx_count_exceeded"
+    }
+
+    fn evaluate(&self, input: &RuleInput<'_>) -> RuleOutcome {
+        let count = input.template.tx_count;
+        let max_allowed = input.cfg.max_tx_count;
+        fail_if(count > max_allowed, || VerdictReason::TxCountExceeded {
+            count,
+            max_allowed,
+        })
+    }
+}
+
+/// Senders that predate observed_weight are not penalized.
+struct MaxWeightRule {
+    max_allowed: u64,
+}
+
+impl PolicyRule for MaxWeightRule {
+    fn name(&self) -> &str {
+        "max_weight"
+    }
+
+    fn reason_code(&self) -> &str {
+        "weight_limit_exceeded"
+    }
+
+    fn evaluate(&self, input: &RuleInput<'_>) -> RuleOutcome {
+        let Some(weight) = input.template.observed_weight else {
+            return RuleOutcome::Pass;
+        };
+        fail_if(weight > self.max_allowed, || {
+            VerdictReason::WeightLimitExceeded {
+                weight,
+                max_allowed: self.max_allowed,
+            }
+        })
+    }
+}
+
+/// 0 disables it. Overlays can change the floor, so it is read per template.
+struct MinTotalFeesRule;
+
+impl PolicyRule for MinTotalFeesRule {
+    fn name(&self) -> &str {
+        "min_total_fees"
+    }
+
+    fn reason_code(&self) -> &str {
+        "total_fees_below_minimum"
+    }
+
+    fn evaluate(&self, input: &RuleInput<'_>) -> RuleOutcome {
+        let total = input.template.total_fees;
+        let min_required = input.cfg.min_total_fees;
+        fail_if(min_required > 0 && total < min_required, || {
+            VerdictReason::TotalFeesBelowMinimum {
+                total,
+                min_required,
+            }
+        })
+    }
+}
+
+/// Floor of the selected tier; 0 disables it.
+struct MinAvgFeeRule;
+
+impl PolicyRule for MinAvgFeeRule {
+    fn name(&self) -> &str {
+        "min_avg_fee"
+    }
+
+    fn reason_code(&self) -> &str {
+        "avg_fee_below_minimum"
+    }
+
+    fn evaluate(&self, input: &RuleInput<'_>) -> RuleOutcome {
+        let t = input.template;
+        if input.min_avg_fee == 0 || t.tx_count == 0 {
+            return RuleOutcome::Pass;
+        }
+        let avg = t.total_fees / t.tx_count as u64;
+        fail_if(avg < input.min_avg_fee, || {
+            VerdictReason::AvgFeeBelowMinimum {
+                avg,
+                min_required: input.min_avg_fee,
+            }
+        })
+    }
+}
+
+/// Floor of the selected tier; 0 disables it, and templates without vsize skip it.
+struct MinFeerateRule;
+
+impl PolicyRule for MinFeerateRule {
+    fn name(&self) -> &str {
+        "min_feerate"
+    }
+
+    fn reason_code(&self) -> &str {
+        "feerate_below_minimum"
+    }
+
+    fn evaluate(&self, input: &RuleInput<'_>) -> RuleOutcome {
+        if input.min_feerate <= 0.0 {
+            return RuleOutcome::Pass;
+        }
+        let Some(feerate) = template_feerate_sat_vb(input.template) else {
+            return RuleOutcome::Pass;
+        };
+        fail_if(feerate < input.min_feerate, || {
+            VerdictReason::FeerateBelowMinimum {
+                feerate,
+                min_required: input.min_feerate,
+            }
+        })
+    }
+}
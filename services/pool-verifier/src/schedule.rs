@@ -0,0 +1,391 @@
+//! Named policy overlays (`[[policy.overlays]]`) that override part of the
+//! base policy inside a UTC time window and/or a block height range.
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::policy::{FeeTierConfig, PolicyConfig, RuleMode};
+
+const MINUTES_PER_DAY: u64 = 24 * 60;
+const MS_PER_MINUTE: u64 = 60_000;
+const DAY_NAMES: [&str; 7] = ["mon", "tue", "wed", "thu", "fri", "sat", "sun"];
+/// Block interval used to place height transitions on the clock.
+const TARGET_BLOCK_INTERVAL_MS: u64 = 600_000;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PolicyOverlay {
+    pub name: String,
+
+    /// First block height the overlay covers (inclusive).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_height: Option<u32>,
+    /// Last block height the overlay covers (inclusive).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_height: Option<u32>,
+
+    /// Daily UTC window start, "HH:MM" (inclusive). Needs `until_utc`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub from_utc: Option<String>,
+    /// Daily UTC window end, "HH:MM" (exclusive). Earlier than `from_utc` wraps midnight.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub until_utc: Option<String>,
+    /// UTC weekdays the window opens on ("mon".."sun"). Empty means every day.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub days: Vec<String>,
+
+    // Overrides; unset fields keep the base policy value.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tiers: Option<Vec<FeeTierConfig>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_avg_fee_lo: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_avg_fee_mid: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_avg_fee_hi: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_feerate_lo: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_feerate_mid: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_feerate_hi: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_total_fees: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_tx_count: Option<u32>,
+    /// Merged over the base `rule_modes`.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub rule_modes: BTreeMap<String, RuleMode>,
+}
+
+/// "HH:MM" as minutes after midnight.
+pub fn parse_hhmm(s: &str) -> Option<u64> {
+    let (h, m) = s.trim().split_once(':')?;
+    let (h, m): (u64, u64) = (h.parse().ok()?, m.parse().ok()?);
+    (h < 24 && m < 60).then_some(h * 60 + m)
+}
+
+/// Monday = 0.
+fn weekday(unix_ms: u64) -> usize {
+    // 1970-01-01 was a Thursday.
+    ((unix_ms / (MINUTES_PER_DAY * MS_PER_MINUTE) + 3) % 7) as usize
+}
+
+impl PolicyOverlay {
+    pub fn has_time_window(&self) -> bool {
+        self.from_utc.is_some() || self.until_utc.is_some()
+    }
+
+    pub fn has_height_range(&self) -> bool {
+        self.min_height.is_some() || self.max_height.is_some()
+    }
+
+    fn height_matches(&self, height: Option<u32>) -> bool {
+        if !self.has_height_range() {
+            return true;
+        }
+        let Some(h) = height else {
+            return false;
+        };
+        self.min_height.is_none_or(|min| h >= min) && self.max_height.is_none_or(|max| h <= max)
+    }
+
+    fn time_matches(&self, unix_ms: u64) -> bool {
+        if !self.has_time_window() {
+            return true;
+        }
+        let (Some(from), Some(until)) = (
+            self.from_utc.as_deref().and_then(parse_hhmm),
+            self.until_utc.as_deref().and_then(parse_hhmm),
+        ) else {
+            return false;
+        };
+
+        let minute = (unix_ms / MS_PER_MINUTE) % MINUTES_PER_DAY;
+        let today = weekday(unix_ms);
+        let opens_on = |day: usize| {
+            self.days.is_empty()
+                || self
+                    .days
+                    .iter()
+                    .any(|d| d.eq_ignore_ascii_case(DAY_NAMES[day]))
+        };
+
+        if from <= until {
+            minute >= from && minute < until && opens_on(today)
+        } else if minute >= from {
+            opens_on(today)
+        } else {
+            // After midnight in a window that opened yesterday.
+            minute < until && opens_on((today + 6) % 7)
+        }
+    }
+
+    /// Every condition the overlay sets must hold. `height` is None when unknown,
+    /// which never matches a height range.
+    pub fn is_active(&self, height: Option<u32>, unix_ms: u64) -> bool {
+        self.height_matches(height) && self.time_matches(unix_ms)
+    }
+
+    pub fn apply(&self, cfg: &mut PolicyConfig) {
+        if let Some(ref tiers) = self.tiers {
+            cfg.tiers = tiers.clone();
+        }
+        if let Some(v) = self.min_avg_fee_lo {
+            cfg.min_avg_fee_lo = v;
+        }
+        if let Some(v) = self.min_avg_fee_mid {
+            cfg.min_avg_fee_mid = v;
+        }
+        if let Some(v) = self.min_avg_fee_hi {
+            cfg.min_avg_fee_hi = v;
+        }
+        if let Some(v) = self.min_feerate_lo {
+            cfg.min_feerate_lo = v;
+        }
+        if let Some(v) = self.min_feerate_mid {
+            cfg.min_feerate_mid = v;
+        }
+        if let Some(v) = self.min_feerate_hi {
+            cfg.min_feerate_hi = v;
+        }
+        if let Some(v) = self.min_total_fees {
+            cfg.min_total_fees = v;
+        }
+        if let Some(v) = self.max_tx_count {
+            cfg.max_tx_count = v;
+        }
+        for (code, mode) in &self.rule_modes {
+            cfg.rule_modes.insert(code.clone(), *mode);
+        }
+    }
+
+    /// Example with every field set, for key checking.
+    pub(crate) fn key_sample() -> Self {
+        Self {
+            name: String::new(),
+            min_height: Some(0),
+            max_height: Some(0),
+            from_utc: Some(String::new()),
+            until_utc: Some(String::new()),
+            days: vec![String::new()],
+            tiers: Some(Vec::new()),
+            min_avg_fee_lo: Some(0),
+            min_avg_fee_mid: Some(0),
+            min_avg_fee_hi: Some(0),
+            min_feerate_lo: Some(0.0),
+            min_feerate_mid: Some(0.0),
+            min_feerate_hi: Some(0.0),
+            min_total_fees: Some(0),
+            max_tx_count: Some(0),
+            rule_modes: BTreeMap::new(),
+        }
+    }
+}
+
+pub fn is_day_name(s: &str) -> bool {
+    DAY_NAMES.iter().any(|d| d.eq_ignore_ascii_case(s))
+}
+
+/// Upcoming change of the active overlay.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct OverlayTransition {
+    /// Overlay active after the change; None means the base policy.
+    pub overlay: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub at_unix_ms: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub at_height: Option<u32>,
+    /// For height transitions: `at_height` assuming ten-minute blocks.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub estimated_at_unix_ms: Option<u64>,
+}
+
+impl OverlayTransition {
+    fn sort_key(&self) -> u64 {
+        self.at_unix_ms
+            .or(self.estimated_at_unix_ms)
+            .unwrap_or(u64::MAX)
+    }
+}
+
+impl PolicyConfig {
+    /// Name of the first overlay active at `height` / `unix_ms`.
+    pub fn active_overlay(&self, height: Option<u32>, unix_ms: u64) -> Option<&PolicyOverlay> {
+        self.overlays.iter().find(|o| o.is_active(height, unix_ms))
+    }
+
+    /// The policy to evaluate with: the base policy with the first active overlay
+    /// applied, plus that overlay's name.
+    pub fn with_active_overlay(
+        mut self,
+        height: u32,
+        unix_ms: u64,
+    ) -> (PolicyConfig, Option<String>) {
+        match self.active_overlay(Some(height), unix_ms).cloned() {
+            Some(o) => {
+                o.apply(&mut self);
+                (self, Some(o.name))
+            }
+            None => (self, None),
+        }
+    }
+
+    /// Next time-driven change of the active overlay within a week, holding the
+    /// height fixed.
+    pub fn next_time_transition(
+        &self,
+        height: Option<u32>,
+        now_ms: u64,
+    ) -> Option<OverlayTransition> {
+        let current = self.active_overlay(height, now_ms).map(|o| o.name.as_str());
+
+        let mut boundaries: Vec<u64> = self
+            .overlays
+            .iter()
+            .flat_map(|o| [o.from_utc.as_deref(), o.until_utc.as_deref()])
+            .flatten()
+            .filter_map(parse_hhmm)
+            .collect();
+        boundaries.sort_unstable();
+        boundaries.dedup();
+        if boundaries.is_empty() {
+            return None;
+        }
+
+        let day_ms = MINUTES_PER_DAY * MS_PER_MINUTE;
+        let day_start = now_ms - now_ms % day_ms;
+        (0..=7u64)
+            .flat_map(|d| {
+                boundaries
+                    .iter()
+                    .map(move |m| day_start + d * day_ms + m * MS_PER_MINUTE)
+            })
+            .filter(|&t| t > now_ms)
+            .find_map(|t| {
+                let next = self.active_overlay(height, t).map(|o| o.name.as_str());
+                (next != current).then(|| OverlayTransition {
+                    overlay: next.map(str::to_string),
+                    at_unix_ms: Some(t),
+                    at_height: None,
+                    estimated_at_unix_ms: None,
+                })
+            })
+    }
+
+    /// Next height-driven change of the active overlay, holding the time fixed.
+    pub fn next_height_transition(&self, height: u32, now_ms: u64) -> Option<OverlayTransition> {
+        let current = self
+            .active_overlay(Some(height), now_ms)
+            .map(|o| o.name.as_str());
+
+        let mut boundaries: Vec<u32> = self
+            .overlays
+            .iter()
+            .flat_map(|o| [o.min_height, o.max_height.map(|h| h.saturating_add(1))])
+            .flatten()
+            .filter(|&h| h > height)
+            .collect();
+        boundaries.sort_unstable();
+        boundaries.dedup();
+
+        boundaries.into_iter().find_map(|h| {
+            let next = self
+                .active_overlay(Some(h), now_ms)
+                .map(|o| o.name.as_str());
+            (next != current).then(|| OverlayTransition {
+                overlay: next.map(str::to_string),
+                at_unix_ms: None,
+                at_height: Some(h),
+                estimated_at_unix_ms: Some(
+                    now_ms + u64::from(h - height) * TARGET_BLOCK_INTERVAL_MS,
+                ),
+            })
+        })
+    }
+
+    /// Whichever of the next time and height transitions comes first. `height` is
+    /// the next block's height, None when no chain tip is known.
+    pub fn next_overlay_transition(
+        &self,
+        height: Option<u32>,
+        now_ms: u64,
+    ) -> Option<OverlayTransition> {
+        let by_time = self.next_time_transition(height, now_ms);
+        let by_height = height.and_then(|h| self.next_height_transition(h, now_ms));
+        by_time
+            .into_iter()
+            .chain(by_height)
+            .min_by_key(|t| t.sort_key())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 2024-01-01 00:00 UTC, a Monday.
+    const MONDAY_MS: u64 = 1_704_067_200_000;
+
+    /// `day` counts from Monday = 0 in the week of `MONDAY_MS`.
+    fn at(day: u64, hour: u64, minute: u64) -> u64 {
+        MONDAY_MS + (day * MINUTES_PER_DAY + hour * 60 + minute) * MS_PER_MINUTE
+    }
+
+    fn window(from: &str, until: &str, days: &[&str]) -> PolicyOverlay {
+        let mut o = PolicyOverlay::key_sample();
+        o.min_height = None;
+        o.max_height = None;
+        o.from_utc = Some(from.to_string());
+        o.until_utc = Some(until.to_string());
+        o.days = days.iter().map(|d| d.to_string()).collect();
+        o
+    }
+
+    #[test]
+    fn weekday_counts_from_monday() {
+        assert_eq!(weekday(at(0, 0, 0)), 0);
+        assert_eq!(weekday(at(4, 23, 59)), 4);
+        assert_eq!(weekday(at(7, 0, 0)), 0);
+    }
+
+    #[test]
+    fn same_day_window_is_half_open() {
+        let o = window("09:00", "17:00", &[]);
+        assert!(!o.time_matches(at(2, 8, 59)));
+        assert!(o.time_matches(at(2, 9, 0)));
+        assert!(o.time_matches(at(2, 16, 59)));
+        assert!(!o.time_matches(at(2, 17, 0)));
+    }
+
+    #[test]
+    fn midnight_window_belongs_to_the_day_it_opens() {
+        // Opens Friday 22:00, closes Saturday 02:00.
+        let o = window("22:00", "02:00", &["fri"]);
+        assert!(!o.time_matches(at(4, 21, 59)));
+        assert!(o.time_matches(at(4, 22, 0)));
+        assert!(o.time_matches(at(5, 1, 59)));
+        assert!(!o.time_matches(at(5, 2, 0)));
+
+        // Friday early morning is Thursday's window; Saturday night is not listed.
+        assert!(!o.time_matches(at(4, 1, 0)));
+        assert!(!o.time_matches(at(5, 23, 0)));
+    }
+
+    #[test]
+    fn midnight_window_wraps_the_week() {
+        // Sunday night spills into Monday morning.
+        let o = window("23:00", "01:00", &["sun"]);
+        assert!(o.time_matches(at(6, 23, 30)));
+        assert!(o.time_matches(at(7, 0, 30)));
+        assert!(!o.time_matches(at(7, 1, 0)));
+        assert!(!o.time_matches(at(7, 23, 30)));
+    }
+
+    #[test]
+    fn unparsable_window_never_matches() {
+        let o = window("25:00", "02:00", &[]);
+        assert!(!o.time_matches(at(0, 1, 0)));
+        assert!(!o.is_active(None, at(0, 1, 0)));
+    }
+}
@@ -0,0 +1,302 @@
+//! `[[policy.script_rules]]`: small Rhai scripts run as `PolicyRule`s.
+//!
+//! Each script sees `template`, `mempool` (or `()`), `chain_tip` (or `()`),
+//! `tier`, `min_avg_fee`, `min_feerate` and `received_at_unix_ms`, and returns
+//! `true` to pass, `false` to fail, or a string to fail with that detail.
+//! Scripts are compiled when the policy is parsed; a compile error is kept on
+//! the rule and reported by `PolicyConfig::validation_report`.
+
+use std::sync::{Arc, OnceLock};
+
+use rhai::{AST, Dynamic, Engine, Scope};
+use serde::{Deserialize, Serialize};
+
+use crate::policy::VerdictReason;
+use crate::rules::{PolicyRule, RuleInput, RuleOutcome};
+
+/// Per-run budget: Rhai operations (roughly one per expression step).
+pub const SCRIPT_MAX_OPERATIONS: u64 = 50_000;
+const SCRIPT_MAX_CALL_LEVELS: usize = 16;
+const SCRIPT_MAX_EXPR_DEPTH: usize = 64;
+const SCRIPT_MAX_STRING_SIZE: usize = 4_096;
+const SCRIPT_MAX_COLLECTION_SIZE: usize = 1_024;
+
+/// Shared sandboxed engine: no module imports, no `eval`, no printing, and
+/// hard limits so a bad script cannot stall the verdict path.
+fn engine() -> &'static Engine {
+    static ENGINE: OnceLock<Engine> = OnceLock::new();
+    ENGINE.get_or_init(|| {
+        let mut e = Engine::new();
+        e.set_module_resolver(rhai::module_resolvers::DummyModuleResolver::new());
+        e.disable_symbol("eval");
+        e.on_print(|_| {});
+        e.on_debug(|_, _, _| {});
+        e.set_max_operations(SCRIPT_MAX_OPERATIONS);
+        e.set_max_call_levels(SCRIPT_MAX_CALL_LEVELS);
+        e.set_max_expr_depths(SCRIPT_MAX_EXPR_DEPTH, SCRIPT_MAX_EXPR_DEPTH);
+        e.set_max_string_size(SCRIPT_MAX_STRING_SIZE);
+        e.set_max_array_size(SCRIPT_MAX_COLLECTION_SIZE);
+        e.set_max_map_size(SCRIPT_MAX_COLLECTION_SIZE);
+        e
+    })
+}
+
+/// One `[[policy.script_rules]]` entry as written in the file.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct ScriptRuleSpec {
+    name: String,
+    reason_code: String,
+    script: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    detail: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(from = "ScriptRuleSpec", into = "ScriptRuleSpec")]
+pub struct ScriptRule {
+    pub name: String,
+    /// Code reported on failure; also the rule's `[policy.rule_modes]` key.
+    pub reason_code: String,
+    pub script: String,
+    /// Detail used when the script returns `false`.
+    pub detail: Option<String>,
+    compiled: Result<Arc<AST>, String>,
+}
+
+impl From<ScriptRuleSpec> for ScriptRule {
+    fn from(spec: ScriptRuleSpec) -> Self {
+        let compiled = engine()
+            .compile(&spec.script)
+            .map(Arc::new)
+            .map_err(|e| e.to_string());
+        Self {
+            name: spec.name,
+            reason_code: spec.reason_code,
+            script: spec.script,
+            detail: spec.detail,
+            compiled,
+        }
+    }
+}
+
+impl From<ScriptRule> for ScriptRuleSpec {
+    fn from(rule: ScriptRule) -> Self {
+        Self {
+            name: rule.name,
+            reason_code: rule.reason_code,
+            script: rule.script,
+            detail: rule.detail,
+        }
+    }
+}
+
+impl ScriptRule {
+    pub fn new(name: &str, reason_code: &str, script: &str) -> Self {
+        ScriptRuleSpec {
+            name: name.to_string(),
+            reason_code: reason_code.to_string(),
+            script: script.to_string(),
+            detail: None,
+        }
+        .into()
+    }
+
+    /// Why the script failed to compile, if it did.
+    pub fn compile_error(&self) -> Option<&str> {
+        self.compiled.as_ref().err().map(String::as_str)
+    }
+
+    /// Example with every field set, for key checking.
+    pub(crate) fn key_sample() -> Self {
+        let mut s = Self::new("", "", "true");
+        s.detail = Some(String::new());
+        s
+    }
+
+    fn scope(input: &RuleInput<'_>) -> Result<Scope<'static>, String> {
+        let to_dynamic = |v: Result<Dynamic, _>| v.map_err(|e| format!("input conversion: {e}"));
+        let optional = |v: Option<Result<Dynamic, _>>| match v {
+            Some(v) => to_dynamic(v),
+            None => Ok(Dynamic::UNIT),
+        };
+
+        let mut scope = Scope::new();
+        scope.push_constant(
+            "template",
+            to_dynamic(rhai::serde::to_dynamic(input.template))?,
+        );
+        scope.push_constant(
+            "mempool",
+            optional(input.ctx.mempool.map(rhai::serde::to_dynamic))?,
+        );
+        scope.push_constant(
+            "chain_tip",
+            optional(input.ctx.chain_tip.map(rhai::serde::to_dynamic))?,
+        );
+        scope.push_constant("tier", input.tier.as_str().to_string());
+        scope.push_constant("min_avg_fee", input.min_avg_fee as rhai::INT);
+        scope.push_constant("min_feerate", input.min_feerate);
+        scope.push_constant(
+            "received_at_unix_ms",
+            input
+                .ctx
+                .received_at_unix_ms
+                .map(|ms| Dynamic::from(ms as rhai::INT))
+                .unwrap_or(Dynamic::UNIT),
+        );
+        Ok(scope)
+    }
+
+    fn fail(&self, detail: String) -> RuleOutcome {
+        RuleOutcome::Fail(VerdictReason::Custom {
+            code: self.reason_code.clone(),
+            detail,
+            threshold: None,
+            observed: None,
+        })
+    }
+}
+
+impl PolicyRule for ScriptRule {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn reason_code(&self) -> &str {
+        &self.reason_code
+    }
+
+    /// Runtime errors, including exceeded limits, fail the rule: a policy
+    /// check that cannot run is not a pass.
+    fn evaluate(&self, input: &RuleInput<'_>) -> RuleOutcome {
+        let ast = match &self.compiled {
+            Ok(ast) => ast,
+            Err(e) => return self.fail(format!("script rule {} does not compile: {e}", self.name)),
+        };
+        let mut scope = match Self::scope(input) {
+            Ok(s) => s,
+            Err(e) => return self.fail(format!("script rule {}: {e}", self.name)),
+        };
+
+        match engine().eval_ast_with_scope::<Dynamic>(&mut scope, ast) {
+            Ok(v) if v.is_bool() => {
+                if v.as_bool().unwrap_or(false) {
+                    RuleOutcome::Pass
+                } else {
+                    self.fail(
+                        self.detail
+                            .clone()
+                            .unwrap_or_else(|| format!("script rule {} failed", self.name)),
+                    )
+                }
+            }
+            Ok(v) if v.is_string() => self.fail(v.into_string().unwrap_or_default()),
+            Ok(v) => self.fail(format!(
+                "script rule {} returned {}, expected bool or string",
+                self.name,
+                v.type_name()
+            )),
+            Err(e) => self.fail(format!("script rule {} error: {e}", self.name)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rg_protocol::TemplatePropose;
+
+    use crate::policy::{EvalContext, FeeTier, PolicyConfig};
+
+    fn propose() -> TemplatePropose {
+        TemplatePropose {
+            version: 2,
+            id: 1,
+            block_height: 840_000,
+            prev_hash: "00".repeat(32),
+            coinbase_value: 312_500_000,
+            tx_count: 3,
+            total_fees: 3_000,
+            observed_weight: None,
+            created_at_unix_ms: None,
+            total_vsize: None,
+            coinbase_value_substituted: false,
+        }
+    }
+
+    fn run(script: &str) -> RuleOutcome {
+        let template = propose();
+        let cfg = PolicyConfig::default_with_protocol(2);
+        let tier = FeeTier {
+            index: 0,
+            name: "low".to_string(),
+        };
+        let input = RuleInput {
+            template: &template,
+            cfg: &cfg,
+            tier: &tier,
+            min_avg_fee: 500,
+            min_feerate: 0.0,
+            ctx: EvalContext {
+                chain_tip: None,
+                received_at_unix_ms: None,
+                mempool: None,
+            },
+        };
+        ScriptRule::new("test", "custom_check", script).evaluate(&input)
+    }
+
+    fn fail_detail(outcome: RuleOutcome) -> String {
+        match outcome {
+            RuleOutcome::Fail(VerdictReason::Custom { code, detail, .. }) => {
+                assert_eq!(code, "custom_check");
+                detail
+            }
+            other => panic!("expected a custom failure, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn return_values_map_to_outcomes() {
+        assert!(matches!(run("template.tx_count == 3"), RuleOutcome::Pass));
+        assert_eq!(
+            fail_detail(run("min_avg_fee > 1000")),
+            "script rule test failed"
+        );
+        assert_eq!(fail_detail(run(r#""too few txs""#)), "too few txs");
+        assert!(fail_detail(run("42")).contains("expected bool or string"));
+    }
+
+    #[test]
+    fn unknown_context_is_unit() {
+        assert!(matches!(
+            run("mempool == () && chain_tip == () && received_at_unix_ms == ()"),
+            RuleOutcome::Pass
+        ));
+    }
+
+    #[test]
+    fn runtime_error_fails_the_rule() {
+        let detail = fail_detail(run("let x = template.tx_count / 0; true"));
+        assert!(detail.starts_with("script rule test error:"), "{detail}");
+    }
+
+    #[test]
+    fn operation_limit_stops_endless_loop() {
+        let detail = fail_detail(run("loop { } true"));
+        assert!(detail.contains("operations"), "{detail}");
+    }
+
+    #[test]
+    fn call_depth_limit_stops_recursion() {
+        let detail = fail_detail(run("fn down(n) { down(n + 1) } down(0)"));
+        assert!(detail.contains("Stack overflow"), "{detail}");
+    }
+
+    #[test]
+    fn compile_error_is_kept_and_fails_the_rule() {
+        let rule = ScriptRule::new("test", "custom_check", "eval(\"true\")");
+        assert!(rule.compile_error().is_some());
+        assert!(fail_detail(run("eval(\"true\")")).contains("does not compile"));
+    }
+}
@@ -1,20 +1,511 @@
+use std::collections::{BTreeMap, VecDeque};
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
 use anyhow::{Context, anyhow};
 use rg_protocol::PROTOCOL_VERSION;
+use serde::{Deserialize, Serialize};
+
+use pool_verifier::chain_tip::ChainTip;
+use pool_verifier::policy::{PolicyConfig, ValidationReport};
+use pool_verifier::policy_lint::{PolicyLint, lint_policy_text};
+use pool_verifier::rules::RuleRegistry;
+use pool_verifier::tier_state::TierState;
 
-use pool_verifier::policy::PolicyConfig;
+/// Older revisions are dropped from memory past this; their version numbers are never reused.
+pub const POLICY_HISTORY_LIMIT: usize = 100;
+/// Append-only record of every revision, so versions keep increasing across restarts.
+pub const POLICY_HISTORY_PATH: &str = "data/policy_history.log";
+/// At startup, a history file with more entries than this is rewritten down to
+/// the last `POLICY_HISTORY_LIMIT` revisions.
+const POLICY_HISTORY_COMPACT_AT: usize = 2 * POLICY_HISTORY_LIMIT;
 
 #[derive(Debug, Clone)]
 pub struct PolicyHolder {
     pub config: PolicyConfig,
+    /// Rules for `config`, rebuilt on every install rather than per template.
+    pub rules: Arc<RuleRegistry>,
+    pub toml_text: String,
+    /// Version of the active policy; matches the newest `history` entry.
+    pub version: u64,
+    /// Oldest first, at most `POLICY_HISTORY_LIMIT` entries.
+    pub history: VecDeque<PolicyRevision>,
+    /// Unknown keys and defaulted fields in `toml_text`.
+    pub lint: PolicyLint,
+    /// Why the policy file was last rejected (startup load or hot reload).
+    /// Cleared once the file and the active policy agree again.
+    pub file_error: Option<PolicyFileError>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PolicyFileError {
+    pub error: String,
+    pub at_unix_ms: u64,
+}
+
+/// How a policy revision became active.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PolicySource {
+    /// Loaded from `VELDRA_POLICY_FILE` at startup.
+    File,
+    /// Built-in degraded-mode policy, used when the file failed to load.
+    BuiltinDefault,
+    /// `POST /policy/apply`.
+    Apply,
+    /// `POST /policy/apply_toml`.
+    ApplyToml,
+    /// `POST /policy/rollback/{version}`.
+    Rollback,
+    /// `VELDRA_POLICY_FILE` changed on disk or SIGHUP.
+    Reload,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PolicyRevision {
+    pub version: u64,
+    pub set_at_unix_ms: u64,
+    pub source: PolicySource,
+    /// For rollbacks, the version whose policy was restored.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub restored_from: Option<u64>,
+    pub toml_text: String,
+    #[serde(skip)]
+    pub config: PolicyConfig,
+}
+
+/// On-disk form of `PolicyRevision`; the config is re-parsed from `toml_text`.
+#[derive(Deserialize)]
+struct StoredRevision {
+    version: u64,
+    set_at_unix_ms: u64,
+    source: PolicySource,
+    #[serde(default)]
+    restored_from: Option<u64>,
+    toml_text: String,
+}
+
+impl PolicyHolder {
+    fn new(config: PolicyConfig, toml_text: String, source: PolicySource) -> Self {
+        let mut history = VecDeque::new();
+        history.push_back(PolicyRevision {
+            version: 1,
+            set_at_unix_ms: now_unix_ms(),
+            source,
+            restored_from: None,
+            toml_text: toml_text.clone(),
+            config: config.clone(),
+        });
+        Self {
+            lint: lint_policy_text(&toml_text),
+            rules: Arc::new(RuleRegistry::from_config(&config)),
+            config,
+            toml_text,
+            version: 1,
+            history,
+            file_error: None,
+        }
+    }
+
+    /// Record `config` as the next version and make it active.
+    fn install(
+        &mut self,
+        config: PolicyConfig,
+        toml_text: String,
+        source: PolicySource,
+        restored_from: Option<u64>,
+    ) -> u64 {
+        let version = self.version + 1;
+        let revision = PolicyRevision {
+            version,
+            set_at_unix_ms: now_unix_ms(),
+            source,
+            restored_from,
+            toml_text: toml_text.clone(),
+            config: config.clone(),
+        };
+        append_policy_revision(POLICY_HISTORY_PATH, &revision);
+        self.history.push_back(revision);
+        while self.history.len() > POLICY_HISTORY_LIMIT {
+            self.history.pop_front();
+        }
+
+        self.lint = lint_policy_text(&toml_text);
+        self.rules = Arc::new(RuleRegistry::from_config(&config));
+        self.config = config;
+        self.toml_text = toml_text;
+        self.version = version;
+        self.file_error = None;
+        version
+    }
+
+    pub fn revision(&self, version: u64) -> Option<&PolicyRevision> {
+        self.history.iter().find(|r| r.version == version)
+    }
+
+    /// Continue numbering from the revisions recorded at `path`: earlier revisions
+    /// are loaded for rollback and the startup policy becomes the next version,
+    /// unless it is the same TOML as the last recorded revision, which then stays
+    /// active under its own version.
+    pub fn resume_history(&mut self, path: &str) {
+        let mut loaded: VecDeque<PolicyRevision> = VecDeque::new();
+        let mut last_version = 0u64;
+        let mut stored_count = 0usize;
+
+        if let Ok(file) = File::open(path) {
+            for line in BufReader::new(file).lines().map_while(Result::ok) {
+                let line = line.trim();
+                if line.is_empty() {
+                    continue;
+                }
+                let Ok(stored) = serde_json::from_str::<StoredRevision>(line) else {
+                    continue;
+                };
+                stored_count += 1;
+                last_version = last_version.max(stored.version);
+
+                // Revisions that no longer parse stay numbered but cannot be restored.
+                let Ok(config) = parse_policy_from_policy_table(&stored.toml_text) else {
+                    continue;
+                };
+                loaded.push_back(PolicyRevision {
+                    version: stored.version,
+                    set_at_unix_ms: stored.set_at_unix_ms,
+                    source: stored.source,
+                    restored_from: stored.restored_from,
+                    toml_text: stored.toml_text,
+                    config,
+                });
+                if loaded.len() >= POLICY_HISTORY_LIMIT {
+                    loaded.pop_front();
+                }
+            }
+        }
+
+        let Some(mut current) = self.history.pop_back() else {
+            return;
+        };
+        let unchanged = loaded.back().is_some_and(|last| {
+            last.version == last_version && last.toml_text == current.toml_text
+        });
+        if unchanged {
+            if let Some(last) = loaded.back() {
+                self.version = last.version;
+            }
+        } else {
+            current.version = last_version + 1;
+            append_policy_revision(path, &current);
+            stored_count += 1;
+            self.version = current.version;
+            loaded.push_back(current);
+            if loaded.len() > POLICY_HISTORY_LIMIT {
+                loaded.pop_front();
+            }
+        }
+        self.history = loaded;
+
+        if stored_count > POLICY_HISTORY_COMPACT_AT {
+            compact_policy_history(path, &self.history);
+        }
+    }
+}
+
+/// Rewrite `path` to hold only `kept`. The newest revision is always kept, so
+/// version numbering still resumes from it.
+fn compact_policy_history(path: &str, kept: &VecDeque<PolicyRevision>) {
+    let mut contents = String::new();
+    for revision in kept {
+        match serde_json::to_string(revision) {
+            Ok(line) => {
+                contents.push_str(&line);
+                contents.push('\n');
+            }
+            Err(e) => {
+                eprintln!("[policy] serialize history entry failed, not compacting: {e:?}");
+                return;
+            }
+        }
+    }
+    match persist_policy_atomic(path, &contents) {
+        Ok(()) => println!(
+            "[policy] compacted {} to the last {} revisions",
+            path,
+            kept.len()
+        ),
+        Err(e) => eprintln!("[policy] compact {} failed: {e:#}", path),
+    }
+}
+
+fn append_policy_revision(path: &str, revision: &PolicyRevision) {
+    let line = match serde_json::to_string(revision) {
+        Ok(l) => l,
+        Err(e) => {
+            eprintln!("[policy] serialize history entry failed: {e:?}");
+            return;
+        }
+    };
+    let res = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .and_then(|mut f| writeln!(f, "{}", line));
+    if let Err(e) = res {
+        eprintln!("[policy] append to {} failed: {e:?}", path);
+    }
+}
+
+fn now_unix_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+/// Candidate policy evaluated next to the active one. Never affects verdicts.
+#[derive(Debug, Clone)]
+pub struct ShadowPolicy {
+    pub config: PolicyConfig,
+    pub rules: Arc<RuleRegistry>,
     pub toml_text: String,
+    pub set_at_unix_ms: u64,
+
+    /// Separate from the active tier state so shadow hysteresis cannot move the live tier.
+    pub tier_state: TierState,
+
+    pub agree: u64,
+    pub disagree: u64,
+    /// Keyed by the shared primary reason code ("ok" for accepts).
+    pub agree_by_reason: BTreeMap<String, u64>,
+    /// Keyed by "<active>-><shadow>" primary reason codes.
+    pub disagree_by_reason: BTreeMap<String, u64>,
+}
+
+impl ShadowPolicy {
+    pub fn new(config: PolicyConfig, toml_text: String, now_ms: u64) -> Self {
+        Self {
+            rules: Arc::new(RuleRegistry::from_config(&config)),
+            config,
+            toml_text,
+            set_at_unix_ms: now_ms,
+            tier_state: TierState::default(),
+            agree: 0,
+            disagree: 0,
+            agree_by_reason: BTreeMap::new(),
+            disagree_by_reason: BTreeMap::new(),
+        }
+    }
+
+    /// Count one proposal. Verdicts agree when their primary reason codes match,
+    /// which also means the same accept/reject decision. Returns true on agreement.
+    pub fn record(&mut self, active_code: &str, shadow_code: &str) -> bool {
+        if active_code == shadow_code {
+            self.agree += 1;
+            *self
+                .agree_by_reason
+                .entry(active_code.to_string())
+                .or_insert(0) += 1;
+            true
+        } else {
+            self.disagree += 1;
+            *self
+                .disagree_by_reason
+                .entry(format!("{}->{}", active_code, shadow_code))
+                .or_insert(0) += 1;
+            false
+        }
+    }
 }
 
 #[derive(Clone)]
 pub struct AppState {
     pub policy: std::sync::Arc<std::sync::RwLock<PolicyHolder>>,
+    pub tier_state: std::sync::Arc<std::sync::Mutex<TierState>>,
+    /// Latest best block from the chain-tip source (RPC poll or push).
+    pub chain_tip: std::sync::Arc<std::sync::RwLock<Option<ChainTip>>>,
+    pub shadow: std::sync::Arc<std::sync::Mutex<Option<ShadowPolicy>>>,
+    /// `VELDRA_POLICY_FILE`; live applies are written back here.
+    pub policy_path: String,
+    /// Reject policies with unknown keys instead of warning (`VELDRA_POLICY_STRICT`).
+    pub policy_strict: bool,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum CommitError {
+    /// The active policy changed after the caller read the version it edited.
+    #[error("policy changed while applying (edited version {based_on}, active version {active})")]
+    Conflict { based_on: u64, active: u64 },
+
+    #[error("persist failed: {0:#}")]
+    Persist(anyhow::Error),
+}
+
+impl AppState {
+    /// Write an applied policy to `policy_path`, then make it active as the next
+    /// version. The temp file is written and fsynced first; only the rename and the
+    /// install happen under the policy write lock, so verdicts are not held up by
+    /// the disk and the file and memory cannot disagree. With `based_on`, the commit
+    /// fails with `CommitError::Conflict` unless that is still the active version.
+    /// A failed commit leaves the active policy and history untouched. Returns the
+    /// new version.
+    pub async fn commit_policy(
+        &self,
+        config: PolicyConfig,
+        toml_text: String,
+        source: PolicySource,
+        restored_from: Option<u64>,
+        based_on: Option<u64>,
+    ) -> Result<u64, CommitError> {
+        let state = self.clone();
+        tokio::task::spawn_blocking(move || {
+            let staged = StagedPolicyFile::write(&state.policy_path, &toml_text)
+                .map_err(CommitError::Persist)?;
+
+            let mut holder = match state.policy.write() {
+                Ok(g) => g,
+                Err(poisoned) => poisoned.into_inner(),
+            };
+            if let Some(based_on) = based_on
+                && based_on != holder.version
+            {
+                return Err(CommitError::Conflict {
+                    based_on,
+                    active: holder.version,
+                });
+            }
+            staged.commit().map_err(CommitError::Persist)?;
+
+            Ok(holder.install(config, toml_text, source, restored_from))
+        })
+        .await
+        .map_err(|e| CommitError::Persist(anyhow!("policy commit task failed: {e}")))?
+    }
+
+    /// Re-read `policy_path` and swap it in if it parses, validates and matches the
+    /// protocol. Returns the new version, or None when the file already matches the
+    /// active policy. On error the active policy is kept and the error is recorded
+    /// in `PolicyHolder::file_error`.
+    pub fn reload_policy_file(&self) -> anyhow::Result<Option<u64>> {
+        let mut holder = match self.policy.write() {
+            Ok(g) => g,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+
+        let res = std::fs::read_to_string(&self.policy_path)
+            .with_context(|| format!("read policy file failed: {}", self.policy_path))
+            .and_then(|contents| {
+                // Our own applies write the file too; those are already active.
+                if contents == holder.toml_text {
+                    return Ok(None);
+                }
+                let (cfg, _) = parse_policy_checked(&contents, self.policy_strict)
+                    .with_context(|| format!("policy parse failed for {}", self.policy_path))?;
+                cfg.validate().context("policy validation failed")?;
+                enforce_protocol(&cfg)?;
+                Ok(Some((cfg, contents)))
+            });
+
+        match res {
+            Ok(Some((cfg, contents))) => Ok(Some(holder.install(
+                cfg,
+                contents,
+                PolicySource::Reload,
+                None,
+            ))),
+            Ok(None) => {
+                holder.file_error = None;
+                Ok(None)
+            }
+            Err(e) => {
+                holder.file_error = Some(PolicyFileError {
+                    error: format!("{e:#}"),
+                    at_unix_ms: now_unix_ms(),
+                });
+                Err(e)
+            }
+        }
+    }
+}
+
+/// Replace `path` with `contents` via a temp file in the same directory,
+/// fsync, then rename, so a crash leaves either the old file or the new one.
+pub fn persist_policy_atomic(path: &str, contents: &str) -> anyhow::Result<()> {
+    StagedPolicyFile::write(path, contents)?.commit()
+}
+
+/// A fully written and fsynced temp file next to its target, waiting to be
+/// renamed over it. Dropped without `commit`, the temp file is removed.
+struct StagedPolicyFile {
+    tmp: PathBuf,
+    target: PathBuf,
+    dir: PathBuf,
+    committed: bool,
+}
+
+impl StagedPolicyFile {
+    fn write(path: &str, contents: &str) -> anyhow::Result<Self> {
+        // Concurrent commits stage side by side, so each needs its own temp file.
+        static NEXT_TMP: AtomicU64 = AtomicU64::new(0);
+
+        let target = PathBuf::from(path);
+        let dir = target
+            .parent()
+            .filter(|p| !p.as_os_str().is_empty())
+            .unwrap_or(Path::new("."))
+            .to_path_buf();
+        let file_name = target
+            .file_name()
+            .ok_or_else(|| anyhow!("policy path has no file name: {}", path))?;
+        let tmp = dir.join(format!(
+            ".{}.{}.tmp",
+            file_name.to_string_lossy(),
+            NEXT_TMP.fetch_add(1, Ordering::Relaxed)
+        ));
+
+        let staged = Self {
+            tmp,
+            target,
+            dir,
+            committed: false,
+        };
+        let mut f = File::create(&staged.tmp)
+            .with_context(|| format!("create temp policy file failed: {}", staged.tmp.display()))?;
+        f.write_all(contents.as_bytes())
+            .context("write temp policy file failed")?;
+        f.sync_all().context("fsync temp policy file failed")?;
+        Ok(staged)
+    }
+
+    fn commit(mut self) -> anyhow::Result<()> {
+        std::fs::rename(&self.tmp, &self.target).with_context(|| {
+            format!(
+                "rename {} -> {} failed",
+                self.tmp.display(),
+                self.target.display()
+            )
+        })?;
+        self.committed = true;
+
+        // Make the rename itself durable.
+        if let Ok(d) = File::open(&self.dir) {
+            let _ = d.sync_all();
+        }
+        Ok(())
+    }
 }
 
-fn enforce_protocol(cfg: &PolicyConfig) -> anyhow::Result<()> {
+impl Drop for StagedPolicyFile {
+    fn drop(&mut self) {
+        if !self.committed {
+            let _ = std::fs::remove_file(&self.tmp);
+        }
+    }
+}
+
+pub fn enforce_protocol(cfg: &PolicyConfig) -> anyhow::Result<()> {
     if cfg.protocol_version != PROTOCOL_VERSION {
         return Err(anyhow!(
             "policy.protocol_version={} does not match binary PROTOCOL_VERSION={}",
@@ -25,7 +516,7 @@ fn enforce_protocol(cfg: &PolicyConfig) -> anyhow::Result<()> {
     Ok(())
 }
 
-fn parse_policy_from_policy_table(contents: &str) -> anyhow::Result<PolicyConfig> {
+pub fn parse_policy_from_policy_table(contents: &str) -> anyhow::Result<PolicyConfig> {
     let v: toml::Value = toml::from_str(contents).context("parse TOML as value")?;
 
     let policy_v = v
@@ -40,28 +531,85 @@ fn parse_policy_from_policy_table(contents: &str) -> anyhow::Result<PolicyConfig
     Ok(cfg)
 }
 
-pub fn load_initial_policy(path: &str) -> anyhow::Result<PolicyHolder> {
+pub fn policy_strict_from_env() -> bool {
+    std::env::var("VELDRA_POLICY_STRICT")
+        .map(|v| matches!(v.trim(), "1" | "true" | "yes"))
+        .unwrap_or(false)
+}
+
+/// Parse a policy file and lint its `[policy]` table. In strict mode unknown
+/// keys are an error; otherwise they are returned as warnings in the lint.
+pub fn parse_policy_checked(
+    contents: &str,
+    strict: bool,
+) -> anyhow::Result<(PolicyConfig, PolicyLint)> {
+    let cfg = parse_policy_from_policy_table(contents)?;
+    let lint = lint_policy_text(contents);
+
+    if strict && !lint.is_clean() {
+        return Err(anyhow!(
+            "strict policy parsing: {}",
+            lint.warnings().join("; ")
+        ));
+    }
+
+    Ok((cfg, lint))
+}
+
+/// `cfg.validation_report()` plus the lint's unknown keys (code `key.unknown`),
+/// which are errors in strict mode and warnings otherwise.
+pub fn policy_report(cfg: &PolicyConfig, lint: &PolicyLint, strict: bool) -> ValidationReport {
+    let mut report = cfg.validation_report();
+    for key in &lint.unknown_keys {
+        if strict {
+            report.error(key.path.clone(), "key.unknown", key.to_string());
+        } else {
+            report.warning(key.path.clone(), "key.unknown", key.to_string());
+        }
+    }
+    report
+}
+
+pub fn load_initial_policy(path: &str, strict: bool) -> anyhow::Result<PolicyHolder> {
     let contents = std::fs::read_to_string(path)
         .with_context(|| format!("read policy file failed: {}", path))?;
 
-    let cfg = parse_policy_from_policy_table(&contents)
+    let (cfg, _) = parse_policy_checked(&contents, strict)
         .with_context(|| format!("policy parse failed for {}", path))?;
 
     cfg.validate().context("policy validation failed")?;
     enforce_protocol(&cfg)?;
 
-    Ok(PolicyHolder {
-        config: cfg,
-        toml_text: contents,
-    })
+    Ok(PolicyHolder::new(cfg, contents, PolicySource::File))
 }
 
-pub fn safe_initial_policy(path: &str) -> PolicyHolder {
-    match load_initial_policy(path) {
-        Ok(h) => h,
+pub fn safe_initial_policy(path: &str, strict: bool) -> PolicyHolder {
+    match load_initial_policy(path, strict) {
+        Ok(h) => {
+            println!(
+                "[policy] loaded {} (protocol_version={}, {} fee tiers)",
+                path,
+                h.config.protocol_version,
+                h.config.fee_tiers().len()
+            );
+            for w in h.lint.warnings() {
+                eprintln!("[policy] warning: {}", w);
+            }
+            if !h.lint.defaulted_fields.is_empty() {
+                println!(
+                    "[policy] defaulted fields: {}",
+                    h.lint.defaulted_fields.join(", ")
+                );
+            }
+            h
+        }
         Err(e) => {
             eprintln!("[policy] load failed: {e:?}");
             eprintln!("[policy] entering degraded mode with built-in default policy");
+            let file_error = PolicyFileError {
+                error: format!("{e:#}"),
+                at_unix_ms: now_unix_ms(),
+            };
 
             // Use the repo-provided constructor (PolicyConfig is not Default).
             let mut cfg: PolicyConfig = PolicyConfig::default_with_protocol(PROTOCOL_VERSION);
@@ -95,10 +643,13 @@ pub fn safe_initial_policy(path: &str) -> PolicyHolder {
                 }
             }
 
-            PolicyHolder {
-                config: cfg,
-                toml_text: "# policy load failed; running with built-in defaults\n".to_string(),
-            }
+            let mut holder = PolicyHolder::new(
+                cfg,
+                "# policy load failed; running with built-in defaults\n".to_string(),
+                PolicySource::BuiltinDefault,
+            );
+            holder.file_error = Some(file_error);
+            holder
         }
     }
 }
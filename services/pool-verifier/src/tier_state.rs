@@ -0,0 +1,156 @@
+use serde::Serialize;
+
+use crate::mempool::MempoolSnapshot;
+use crate::policy::{FeeTier, PolicyConfig};
+
+/// Sticky fee tier selection. Applies `policy.tier_switching` so a mempool
+/// hovering around a threshold does not flip the floor on every template.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct TierState {
+    pub current: Option<FeeTier>,
+    pub changed_at_unix_ms: Option<u64>,
+}
+
+impl TierState {
+    pub fn select(
+        &mut self,
+        cfg: &PolicyConfig,
+        mempool: Option<&MempoolSnapshot>,
+        now_ms: u64,
+    ) -> FeeTier {
+        let metric = cfg.tier_metric_value(mempool);
+        let raw = cfg.fee_tier_at(cfg.tier_index_for_sample(metric, 0.0));
+
+        // First sample, or the ladder changed under us (policy apply): adopt the raw pick.
+        let Some(current) = self
+            .current
+            .clone()
+            .filter(|c| cfg.fee_tier_at(c.index) == *c)
+        else {
+            return self.switch_to(raw, now_ms);
+        };
+
+        if raw.index == current.index {
+            return current;
+        }
+
+        let band = cfg.tier_switching.hysteresis;
+        let candidate = if metric.is_none() || band == 0.0 {
+            raw
+        } else if raw.index > current.index {
+            let up = cfg.tier_index_for_sample(metric, band);
+            if up > current.index {
+                cfg.fee_tier_at(up)
+            } else {
+                current.clone()
+            }
+        } else {
+            let down = cfg.tier_index_for_sample(metric, -band);
+            if down < current.index {
+                cfg.fee_tier_at(down)
+            } else {
+                current.clone()
+            }
+        };
+
+        if candidate.index == current.index {
+            return current;
+        }
+
+        let dwell_ms = cfg.tier_switching.min_dwell_secs.saturating_mul(1000);
+        let since = self.changed_at_unix_ms.unwrap_or(0);
+        if now_ms.saturating_sub(since) < dwell_ms {
+            return current;
+        }
+
+        self.switch_to(candidate, now_ms)
+    }
+
+    fn switch_to(&mut self, tier: FeeTier, now_ms: u64) -> FeeTier {
+        self.current = Some(tier.clone());
+        self.changed_at_unix_ms = Some(now_ms);
+        tier
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::policy::FeeTierConfig;
+
+    // Legacy ladder: low from 0, mid from 50, high from 500 mempool txs.
+    fn cfg(hysteresis: f64, min_dwell_secs: u64) -> PolicyConfig {
+        let mut cfg = PolicyConfig::default_with_protocol(2);
+        cfg.tier_switching.hysteresis = hysteresis;
+        cfg.tier_switching.min_dwell_secs = min_dwell_secs;
+        cfg
+    }
+
+    fn mempool(tx_count: u64) -> MempoolSnapshot {
+        MempoolSnapshot {
+            tx_count,
+            ..Default::default()
+        }
+    }
+
+    fn pick(state: &mut TierState, cfg: &PolicyConfig, tx_count: u64, now_ms: u64) -> String {
+        state.select(cfg, Some(&mempool(tx_count)), now_ms).name
+    }
+
+    #[test]
+    fn first_sample_adopts_raw_tier() {
+        let cfg = cfg(10.0, 60);
+        let mut state = TierState::default();
+        assert_eq!(pick(&mut state, &cfg, 600, 1_000), "high");
+        assert_eq!(state.changed_at_unix_ms, Some(1_000));
+    }
+
+    #[test]
+    fn hysteresis_band_holds_tier_near_threshold() {
+        let cfg = cfg(10.0, 0);
+        let mut state = TierState::default();
+        assert_eq!(pick(&mut state, &cfg, 40, 0), "low");
+
+        // Up needs threshold + band (60).
+        assert_eq!(pick(&mut state, &cfg, 55, 1), "low");
+        assert_eq!(pick(&mut state, &cfg, 60, 2), "mid");
+
+        // Down needs below threshold - band (40).
+        assert_eq!(pick(&mut state, &cfg, 45, 3), "mid");
+        assert_eq!(pick(&mut state, &cfg, 40, 4), "mid");
+        assert_eq!(pick(&mut state, &cfg, 39, 5), "low");
+        assert_eq!(state.changed_at_unix_ms, Some(5));
+    }
+
+    #[test]
+    fn dwell_blocks_switch_until_elapsed() {
+        let cfg = cfg(0.0, 30);
+        let mut state = TierState::default();
+        assert_eq!(pick(&mut state, &cfg, 10, 0), "low");
+
+        assert_eq!(pick(&mut state, &cfg, 600, 29_999), "low");
+        assert_eq!(state.changed_at_unix_ms, Some(0));
+
+        assert_eq!(pick(&mut state, &cfg, 600, 30_000), "high");
+        assert_eq!(state.changed_at_unix_ms, Some(30_000));
+
+        // The new tier starts its own dwell.
+        assert_eq!(pick(&mut state, &cfg, 10, 45_000), "high");
+    }
+
+    #[test]
+    fn changed_ladder_resets_to_raw_tier() {
+        let mut state = TierState::default();
+        assert_eq!(pick(&mut state, &cfg(0.0, 3_600), 600, 0), "high");
+
+        // Dwell would hold "high", but it no longer exists at that index.
+        let mut cfg = cfg(0.0, 3_600);
+        cfg.tiers = vec![FeeTierConfig {
+            name: "flat".to_string(),
+            threshold: 0.0,
+            min_avg_fee: 0,
+            min_feerate: 0.0,
+        }];
+        assert_eq!(pick(&mut state, &cfg, 600, 1), "flat");
+    }
+}
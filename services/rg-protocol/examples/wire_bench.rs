@@ -0,0 +1,181 @@
+//! JSON vs CBOR benchmark for `MessageCodec`.
+//!
+//!     cargo run --release -p rg-protocol --example wire_bench [iterations]
+//!
+//! Round trips are covered by the codec's unit tests; this only times them.
+
+use std::time::{Duration, Instant};
+
+use bytes::BytesMut;
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use tokio_util::codec::{Decoder, Encoder};
+
+use rg_protocol::{
+    MessageCodec, PROTOCOL_VERSION, PolicyContext, TemplatePropose, TemplateVerdict, VerdictReason,
+    Violation, WireEncoding, decode_message,
+};
+
+const ENCODINGS: [WireEncoding; 2] = [WireEncoding::Json, WireEncoding::Cbor];
+
+fn sample_propose() -> TemplatePropose {
+    TemplatePropose {
+        version: PROTOCOL_VERSION,
+        id: 18_446_744_073_709_551_000,
+        block_height: 840_000,
+        prev_hash: "00000000000000000002a7c4c1e48d76c5a37902165a270156b7a8d72728a054".to_string(),
+        coinbase_value: 312_500_000 + 48_123_456,
+        tx_count: 3_512,
+        total_fees: 48_123_456,
+        observed_weight: Some(3_993_112),
+        created_at_unix_ms: Some(1_713_571_767_000),
+        total_vsize: Some(998_201),
+        coinbase_value_substituted: false,
+    }
+}
+
+fn sample_verdict() -> TemplateVerdict {
+    let violation =
+        |reason: VerdictReason, detail: &str, threshold: f64, observed: f64| Violation {
+            reason_code: reason,
+            reason_detail: Some(detail.to_string()),
+            threshold: Some(threshold),
+            observed: Some(observed),
+        };
+    TemplateVerdict {
+        version: PROTOCOL_VERSION,
+        id: 42,
+        accepted: false,
+        reason_code: Some(VerdictReason::FeerateBelowMinimum),
+        reason_detail: Some("feerate 1.25 sat/vB below 2.0".to_string()),
+        policy_context: Some(PolicyContext {
+            fee_tier: Some("mid".to_string()),
+            min_avg_fee_used: Some(500),
+            min_total_fees_used: Some(0),
+            reject_coinbase_zero: Some(true),
+            unknown_mempool_as_high: Some(false),
+            max_weight_used: Some(3_992_000),
+            feerate_sat_vb: Some(1.25),
+            min_feerate_used: Some(2.0),
+            tier_changed_at_unix_ms: Some(1_713_571_000_000),
+            tier_metric: Some("vbytes".to_string()),
+            tier_metric_value: Some(48_211_003.0),
+            chain_tip_hash: Some("00".repeat(32)),
+            chain_tip_height: Some(839_999),
+            policy_version: Some(7),
+            overlay: Some("weekend".to_string()),
+        }),
+        violations: vec![
+            violation(
+                VerdictReason::FeerateBelowMinimum,
+                "feerate 1.25 sat/vB below 2.0",
+                2.0,
+                1.25,
+            ),
+            violation(
+                VerdictReason::Custom("no_dust_outputs".to_string()),
+                "script rule failed",
+                0.0,
+                3.0,
+            ),
+        ],
+        warnings: vec![violation(
+            VerdictReason::AvgFeeBelowMinimum,
+            "avg fee 13702 below 15000",
+            15_000.0,
+            13_702.0,
+        )],
+    }
+}
+
+fn codec(encoding: WireEncoding) -> MessageCodec {
+    let mut c = MessageCodec::default();
+    c.set_encoding(encoding);
+    c
+}
+
+struct Timing {
+    bytes: usize,
+    encode: Duration,
+    decode: Duration,
+    /// Straight from bytes to `T`, without the codec's `serde_json::Value` step.
+    decode_direct: Duration,
+}
+
+fn bench<T>(encoding: WireEncoding, message: &T, iterations: u32) -> Timing
+where
+    T: DeserializeOwned + Serialize,
+{
+    let mut c = codec(encoding);
+    let mut buf = BytesMut::new();
+
+    let start = Instant::now();
+    for _ in 0..iterations {
+        c.encode(message, &mut buf).expect("encode");
+    }
+    let encode = start.elapsed();
+    let bytes = buf.len() / iterations as usize;
+
+    let mut one = BytesMut::new();
+    codec(encoding).encode(message, &mut one).expect("encode");
+    let start = Instant::now();
+    for _ in 0..iterations {
+        let _: T = match encoding {
+            WireEncoding::Json => serde_json::from_slice(&one).expect("json"),
+            WireEncoding::Cbor => ciborium::from_reader(&one[4..]).expect("cbor"),
+        };
+    }
+    let decode_direct = start.elapsed();
+
+    let start = Instant::now();
+    let mut decoded = 0u32;
+    while let Some(frame) = c.decode(&mut buf).expect("decode") {
+        let _: T = decode_message(&frame.expect("frame"), false).expect("typed decode");
+        decoded += 1;
+    }
+    let decode = start.elapsed();
+    assert_eq!(decoded, iterations);
+
+    Timing {
+        bytes,
+        encode,
+        decode,
+        decode_direct,
+    }
+}
+
+fn report<T>(name: &str, message: &T, iterations: u32)
+where
+    T: DeserializeOwned + Serialize,
+{
+    let per_msg = |d: Duration| d.as_nanos() as f64 / f64::from(iterations) / 1_000.0;
+    let mut json_bytes = 0;
+    for encoding in ENCODINGS {
+        let t = bench(encoding, message, iterations);
+        if encoding == WireEncoding::Json {
+            json_bytes = t.bytes;
+        }
+        println!(
+            "{:<8} {:<4} {:>6} B ({:>5.1}% of json)  encode {:>6.2} us  decode {:>6.2} us  (format only {:>6.2} us)",
+            name,
+            encoding.as_str(),
+            t.bytes,
+            100.0 * t.bytes as f64 / json_bytes as f64,
+            per_msg(t.encode),
+            per_msg(t.decode),
+            per_msg(t.decode_direct),
+        );
+    }
+}
+
+fn main() {
+    let iterations: u32 = std::env::args()
+        .nth(1)
+        .and_then(|s| s.parse().ok())
+        .filter(|n| *n > 0)
+        .unwrap_or(100_000);
+
+    println!("{iterations} messages per run, through MessageCodec and decode_message");
+    report("propose", &sample_propose(), iterations);
+    report("verdict", &sample_verdict(), iterations);
+}
@@ -0,0 +1,197 @@
+//! `VerifierClient`: one handshaken connection to a verifier, sending
+//! `TemplatePropose`s and matching replies to them by `id`.
+
+use std::time::Duration;
+
+use futures_util::{SinkExt, StreamExt};
+use serde::Deserialize;
+use tokio::net::{TcpStream, ToSocketAddrs};
+use tokio::time::timeout;
+use tokio_util::codec::Framed;
+
+use crate::codec::{MessageCodec, WireEncoding, decode_message};
+use crate::{
+    DEFAULT_MAX_LINE_BYTES, ErrorFrame, HandshakeMessage, Hello, Negotiated, ProtocolError,
+    TemplatePropose, TemplateVerdict,
+};
+
+#[derive(Debug, Clone)]
+pub struct ClientConfig {
+    pub connect_timeout: Duration,
+    /// How long to wait for the `HelloAck`.
+    pub handshake_timeout: Duration,
+    /// Send plus wait for the matching verdict.
+    pub request_timeout: Duration,
+    pub max_line_bytes: usize,
+}
+
+impl Default for ClientConfig {
+    fn default() -> Self {
+        Self {
+            connect_timeout: Duration::from_secs(2),
+            handshake_timeout: Duration::from_secs(2),
+            request_timeout: Duration::from_secs(3),
+            max_line_bytes: DEFAULT_MAX_LINE_BYTES,
+        }
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ClientError {
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+
+    #[error("timed out {0}")]
+    Timeout(&'static str),
+
+    #[error("handshake failed: {0}")]
+    Handshake(String),
+
+    /// The verifier answered our request with an `ErrorFrame`.
+    #[error("verifier could not read request: {0}")]
+    Rejected(ProtocolError),
+
+    /// The verifier sent something we could not read.
+    #[error("unreadable reply from verifier: {0}")]
+    Protocol(ProtocolError),
+
+    #[error("verifier closed the connection")]
+    Closed,
+}
+
+/// A line the verifier sends after the handshake.
+#[derive(Debug, Clone)]
+pub enum Reply {
+    Verdict(Box<TemplateVerdict>),
+    Error(ProtocolError),
+}
+
+impl Reply {
+    /// Request the reply belongs to; None for an error frame whose id the
+    /// verifier could not recover.
+    pub fn id(&self) -> Option<u64> {
+        match self {
+            Reply::Verdict(v) => Some(v.id),
+            Reply::Error(e) => e.id,
+        }
+    }
+
+    pub fn from_frame(frame: &serde_json::Value) -> Result<Self, ProtocolError> {
+        if let Ok(ErrorFrame { error }) = ErrorFrame::deserialize(frame) {
+            return Ok(Reply::Error(error));
+        }
+        decode_message(frame, false).map(|v| Reply::Verdict(Box::new(v)))
+    }
+}
+
+pub struct VerifierClient {
+    framed: Framed<TcpStream, MessageCodec>,
+    negotiated: Negotiated,
+    peer_agent: String,
+    request_timeout: Duration,
+}
+
+impl VerifierClient {
+    /// Connect and run the handshake. A verifier that does not answer `hello`
+    /// in time fails with `ClientError::Timeout`.
+    pub async fn connect(
+        addr: impl ToSocketAddrs,
+        hello: &Hello,
+        config: &ClientConfig,
+    ) -> Result<Self, ClientError> {
+        let stream = timeout(config.connect_timeout, TcpStream::connect(addr))
+            .await
+            .map_err(|_| ClientError::Timeout("connecting"))??;
+        Self::handshake(stream, hello, config).await
+    }
+
+    /// Run the handshake on an already connected stream.
+    pub async fn handshake(
+        stream: TcpStream,
+        hello: &Hello,
+        config: &ClientConfig,
+    ) -> Result<Self, ClientError> {
+        let mut framed = Framed::new(stream, MessageCodec::new(config.max_line_bytes));
+
+        let ack = timeout(config.handshake_timeout, async {
+            framed.send(HandshakeMessage::Hello(hello.clone())).await?;
+            match framed.next().await {
+                Some(Ok(Ok(frame))) => Ok(frame),
+                Some(Ok(Err(e))) => Err(ClientError::Protocol(e)),
+                Some(Err(e)) => Err(ClientError::Io(e)),
+                None => Err(ClientError::Closed),
+            }
+        })
+        .await
+        .map_err(|_| ClientError::Timeout("waiting for HelloAck"))??;
+
+        let ack = match HandshakeMessage::deserialize(&ack) {
+            Ok(HandshakeMessage::HelloAck(ack)) => ack,
+            _ => {
+                return Err(ClientError::Handshake(format!(
+                    "expected HelloAck, got {ack}"
+                )));
+            }
+        };
+        let negotiated = hello.accept(&ack).map_err(ClientError::Handshake)?;
+        framed
+            .codec_mut()
+            .set_encoding(WireEncoding::negotiated(&negotiated));
+
+        Ok(Self {
+            framed,
+            negotiated,
+            peer_agent: ack.agent,
+            request_timeout: config.request_timeout,
+        })
+    }
+
+    pub fn negotiated(&self) -> &Negotiated {
+        &self.negotiated
+    }
+
+    /// The verifier's `agent` string from its `HelloAck`.
+    pub fn peer_agent(&self) -> &str {
+        &self.peer_agent
+    }
+
+    /// Send a proposal and wait for the verdict with the same `id`. Replies to
+    /// other ids (late answers to requests that already timed out) are skipped.
+    pub async fn propose(
+        &mut self,
+        propose: &TemplatePropose,
+    ) -> Result<TemplateVerdict, ClientError> {
+        let id = propose.id;
+        timeout(self.request_timeout, async {
+            self.send(propose).await?;
+            loop {
+                match self.recv().await? {
+                    Reply::Verdict(v) if v.id == id => return Ok(*v),
+                    Reply::Error(e) if e.id.is_none_or(|eid| eid == id) => {
+                        return Err(ClientError::Rejected(e));
+                    }
+                    _ => {}
+                }
+            }
+        })
+        .await
+        .map_err(|_| ClientError::Timeout("waiting for verdict"))?
+    }
+
+    /// Send without waiting for the verdict.
+    pub async fn send(&mut self, propose: &TemplatePropose) -> Result<(), ClientError> {
+        self.framed.send(propose).await?;
+        Ok(())
+    }
+
+    /// Next reply from the verifier, whatever request it answers. No timeout;
+    /// cancel-safe, so it can be raced against other work in a `select!`.
+    pub async fn recv(&mut self) -> Result<Reply, ClientError> {
+        match self.framed.next().await {
+            Some(Ok(Ok(frame))) => Reply::from_frame(&frame).map_err(ClientError::Protocol),
+            Some(Ok(Err(e))) => Err(ClientError::Protocol(e)),
+            Some(Err(e)) => Err(ClientError::Io(e)),
+            None => Err(ClientError::Closed),
+        }
+    }
+}
@@ -0,0 +1,523 @@
+//! Framing for the verifier message stream, usable with
+//! `tokio_util::codec::Framed` on either end of a connection. Connections start
+//! with one JSON value per `\n`-terminated line; peers that settle on
+//! `Capability::Cbor` switch to length-prefixed CBOR after the handshake.
+
+use bytes::{Buf, BufMut, BytesMut};
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use tokio_util::codec::{Decoder, Encoder};
+
+use crate::{
+    Capability, DEFAULT_MAX_LINE_BYTES, Negotiated, ProtocolError, ProtocolErrorCode,
+    recover_request_id,
+};
+
+/// Size of the big-endian length in front of each CBOR frame.
+const FRAME_HEADER_BYTES: usize = 4;
+
+/// How messages are laid out on the wire after the handshake. The handshake
+/// itself is always JSON lines.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum WireEncoding {
+    /// One JSON value per `\n`-terminated line.
+    #[default]
+    Json,
+
+    /// A 4-byte big-endian payload length, then one CBOR value. Field names are
+    /// kept, so optional and unknown fields behave as in JSON.
+    Cbor,
+}
+
+impl WireEncoding {
+    /// Encoding to switch to once the handshake settled on `negotiated`.
+    pub fn negotiated(negotiated: &Negotiated) -> Self {
+        if negotiated.has(&Capability::Cbor) {
+            WireEncoding::Cbor
+        } else {
+            WireEncoding::Json
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            WireEncoding::Json => "json",
+            WireEncoding::Cbor => "cbor",
+        }
+    }
+}
+
+impl std::str::FromStr for WireEncoding {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "json" => Ok(WireEncoding::Json),
+            "cbor" => Ok(WireEncoding::Cbor),
+            other => Err(format!(
+                "unknown wire encoding {other:?} (expected \"json\" or \"cbor\")"
+            )),
+        }
+    }
+}
+
+/// Decodes each line or frame to a JSON value and encodes any `Serialize`
+/// value as one.
+///
+/// A line or frame that is too long or cannot be decoded comes out as
+/// `Err(ProtocolError)` rather than an I/O error, so the stream keeps going and
+/// the receiver can answer with an `ErrorFrame`. Blank JSON lines are skipped.
+#[derive(Debug, Clone)]
+pub struct MessageCodec {
+    encoding: WireEncoding,
+    /// Longest accepted JSON line (newline excluded) or CBOR payload.
+    max_line_bytes: usize,
+    /// Where to resume the newline search in a partial line.
+    next_index: usize,
+    /// Dropping the rest of an oversized line; holds the id recovered from its start.
+    discarding: Option<Option<u64>>,
+    /// Bytes of an oversized CBOR frame still to drop.
+    skipping: usize,
+}
+
+impl MessageCodec {
+    pub fn new(max_line_bytes: usize) -> Self {
+        Self {
+            encoding: WireEncoding::Json,
+            max_line_bytes,
+            next_index: 0,
+            discarding: None,
+            skipping: 0,
+        }
+    }
+
+    pub fn max_line_bytes(&self) -> usize {
+        self.max_line_bytes
+    }
+
+    pub fn encoding(&self) -> WireEncoding {
+        self.encoding
+    }
+
+    /// Switch encodings mid-stream, right after the handshake. Bytes already
+    /// buffered are read with the new encoding.
+    pub fn set_encoding(&mut self, encoding: WireEncoding) {
+        self.encoding = encoding;
+    }
+
+    fn too_long(&self, id: Option<u64>) -> ProtocolError {
+        let unit = match self.encoding {
+            WireEncoding::Json => "line",
+            WireEncoding::Cbor => "frame",
+        };
+        ProtocolError {
+            id,
+            code: ProtocolErrorCode::LineTooLong,
+            detail: format!("{unit} exceeds {} bytes", self.max_line_bytes),
+        }
+    }
+
+    fn malformed_cbor(detail: impl Into<String>) -> ProtocolError {
+        ProtocolError {
+            id: None,
+            code: ProtocolErrorCode::MalformedCbor,
+            detail: detail.into(),
+        }
+    }
+
+    fn decode_frame(
+        &mut self,
+        buf: &mut BytesMut,
+    ) -> Option<Result<serde_json::Value, ProtocolError>> {
+        if self.skipping > 0 {
+            let n = self.skipping.min(buf.len());
+            buf.advance(n);
+            self.skipping -= n;
+            if self.skipping > 0 {
+                return None;
+            }
+        }
+        if buf.len() < FRAME_HEADER_BYTES {
+            return None;
+        }
+
+        let mut header = [0u8; FRAME_HEADER_BYTES];
+        header.copy_from_slice(&buf[..FRAME_HEADER_BYTES]);
+        let len = u32::from_be_bytes(header) as usize;
+        if len > self.max_line_bytes {
+            buf.advance(FRAME_HEADER_BYTES);
+            let n = len.min(buf.len());
+            buf.advance(n);
+            self.skipping = len - n;
+            return Some(Err(self.too_long(None)));
+        }
+        if buf.len() < FRAME_HEADER_BYTES + len {
+            buf.reserve(FRAME_HEADER_BYTES + len - buf.len());
+            return None;
+        }
+
+        buf.advance(FRAME_HEADER_BYTES);
+        let payload = buf.split_to(len);
+        Some(ciborium::from_reader(&payload[..]).map_err(|e| Self::malformed_cbor(e.to_string())))
+    }
+
+    fn parse_line(line: &[u8]) -> Result<serde_json::Value, ProtocolError> {
+        let text = std::str::from_utf8(line).map_err(|e| ProtocolError {
+            id: recover_request_id(line),
+            code: ProtocolErrorCode::MalformedJson,
+            detail: format!("line is not UTF-8: {e}"),
+        })?;
+        serde_json::from_str(text).map_err(|e| ProtocolError {
+            id: recover_request_id(line),
+            code: ProtocolErrorCode::MalformedJson,
+            detail: e.to_string(),
+        })
+    }
+}
+
+impl Default for MessageCodec {
+    fn default() -> Self {
+        Self::new(DEFAULT_MAX_LINE_BYTES)
+    }
+}
+
+impl Decoder for MessageCodec {
+    type Item = Result<serde_json::Value, ProtocolError>;
+    type Error = std::io::Error;
+
+    fn decode(&mut self, buf: &mut BytesMut) -> std::io::Result<Option<Self::Item>> {
+        if self.encoding == WireEncoding::Cbor {
+            return Ok(self.decode_frame(buf));
+        }
+
+        loop {
+            let newline = buf[self.next_index..]
+                .iter()
+                .position(|b| *b == b'\n')
+                .map(|i| self.next_index + i);
+
+            if let Some(id) = self.discarding {
+                let Some(end) = newline else {
+                    buf.clear();
+                    self.next_index = 0;
+                    return Ok(None);
+                };
+                buf.advance(end + 1);
+                self.next_index = 0;
+                self.discarding = None;
+                return Ok(Some(Err(self.too_long(id))));
+            }
+
+            let Some(end) = newline else {
+                if buf.len() > self.max_line_bytes {
+                    // Keep nothing of it but the id, so a huge line cannot grow the buffer.
+                    self.discarding = Some(recover_request_id(&buf[..self.max_line_bytes]));
+                    buf.clear();
+                    self.next_index = 0;
+                } else {
+                    self.next_index = buf.len();
+                }
+                return Ok(None);
+            };
+
+            let line = buf.split_to(end + 1);
+            self.next_index = 0;
+            let line = &line[..end];
+            if line.len() > self.max_line_bytes {
+                return Ok(Some(Err(self.too_long(recover_request_id(line)))));
+            }
+            if line.trim_ascii().is_empty() {
+                continue;
+            }
+            return Ok(Some(Self::parse_line(line)));
+        }
+    }
+
+    fn decode_eof(&mut self, buf: &mut BytesMut) -> std::io::Result<Option<Self::Item>> {
+        if let Some(item) = self.decode(buf)? {
+            return Ok(Some(item));
+        }
+        if self.encoding == WireEncoding::Cbor {
+            // Only a cut-off frame can be left over.
+            self.skipping = 0;
+            if buf.is_empty() {
+                return Ok(None);
+            }
+            buf.clear();
+            return Ok(Some(Err(Self::malformed_cbor(
+                "connection closed mid-frame",
+            ))));
+        }
+        if let Some(id) = self.discarding.take() {
+            return Ok(Some(Err(self.too_long(id))));
+        }
+        // A last line without a newline still counts.
+        let line = buf.split();
+        self.next_index = 0;
+        if line.trim_ascii().is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(Self::parse_line(&line)))
+    }
+}
+
+impl<T: Serialize> Encoder<T> for MessageCodec {
+    type Error = std::io::Error;
+
+    fn encode(&mut self, item: T, dst: &mut BytesMut) -> std::io::Result<()> {
+        match self.encoding {
+            WireEncoding::Json => {
+                serde_json::to_writer(dst.writer(), &item)?;
+                dst.put_u8(b'\n');
+            }
+            WireEncoding::Cbor => {
+                let start = dst.len();
+                dst.put_u32(0);
+                if let Err(e) = ciborium::into_writer(&item, dst.writer()) {
+                    dst.truncate(start);
+                    return Err(std::io::Error::other(e.to_string()));
+                }
+                let len = u32::try_from(dst.len() - start - FRAME_HEADER_BYTES)
+                    .map_err(|_| std::io::Error::other("frame longer than 4 GiB"))?;
+                dst[start..start + FRAME_HEADER_BYTES].copy_from_slice(&len.to_be_bytes());
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Read a decoded frame as `T`. In strict mode, keys `T` does not define are
+/// an error instead of being ignored.
+pub fn decode_message<T>(frame: &serde_json::Value, strict: bool) -> Result<T, ProtocolError>
+where
+    T: DeserializeOwned + Serialize,
+{
+    let id = frame.get("id").and_then(serde_json::Value::as_u64);
+    let message = T::deserialize(frame).map_err(|e| ProtocolError {
+        id,
+        code: ProtocolErrorCode::InvalidMessage,
+        detail: format!("not a {}: {e}", short_type_name::<T>()),
+    })?;
+
+    if strict
+        && let (Some(given), Ok(serde_json::Value::Object(known))) =
+            (frame.as_object(), serde_json::to_value(&message))
+    {
+        let unknown: Vec<&str> = given
+            .keys()
+            .filter(|k| !known.contains_key(*k))
+            .map(String::as_str)
+            .collect();
+        if !unknown.is_empty() {
+            return Err(ProtocolError {
+                id,
+                code: ProtocolErrorCode::UnknownField,
+                detail: format!("unknown fields: {}", unknown.join(", ")),
+            });
+        }
+    }
+
+    Ok(message)
+}
+
+fn short_type_name<T>() -> &'static str {
+    let name = std::any::type_name::<T>();
+    name.rsplit("::").next().unwrap_or(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        ErrorFrame, PROTOCOL_VERSION, PolicyContext, TemplatePropose, TemplateVerdict,
+        VerdictReason, Violation,
+    };
+
+    const ENCODINGS: [WireEncoding; 2] = [WireEncoding::Json, WireEncoding::Cbor];
+
+    fn sample_propose() -> TemplatePropose {
+        TemplatePropose {
+            version: PROTOCOL_VERSION,
+            id: 18_446_744_073_709_551_000,
+            block_height: 840_000,
+            prev_hash: "00000000000000000002a7c4c1e48d76c5a37902165a270156b7a8d72728a054"
+                .to_string(),
+            coinbase_value: 312_500_000 + 48_123_456,
+            tx_count: 3_512,
+            total_fees: 48_123_456,
+            observed_weight: Some(3_993_112),
+            created_at_unix_ms: Some(1_713_571_767_000),
+            total_vsize: None,
+            coinbase_value_substituted: false,
+        }
+    }
+
+    fn sample_verdict() -> TemplateVerdict {
+        let violation = Violation {
+            reason_code: VerdictReason::Custom("no_dust_outputs".to_string()),
+            reason_detail: Some("script rule failed".to_string()),
+            threshold: Some(0.0),
+            observed: Some(3.0),
+        };
+        TemplateVerdict {
+            version: PROTOCOL_VERSION,
+            id: 42,
+            accepted: false,
+            reason_code: Some(VerdictReason::FeerateBelowMinimum),
+            reason_detail: Some("feerate 1.25 sat/vB below 2.0".to_string()),
+            policy_context: Some(PolicyContext {
+                fee_tier: Some("mid".to_string()),
+                feerate_sat_vb: Some(1.25),
+                ..PolicyContext::default()
+            }),
+            violations: vec![violation.clone()],
+            warnings: vec![violation],
+        }
+    }
+
+    fn sample_error() -> ErrorFrame {
+        ErrorFrame {
+            error: ProtocolError {
+                id: Some(9),
+                code: ProtocolErrorCode::InvalidMessage,
+                detail: "not a TemplatePropose: missing field `block_height`".to_string(),
+            },
+        }
+    }
+
+    fn codec(encoding: WireEncoding, max_line_bytes: usize) -> MessageCodec {
+        let mut c = MessageCodec::new(max_line_bytes);
+        c.set_encoding(encoding);
+        c
+    }
+
+    /// Decode everything in `wire`, fed `chunk` bytes at a time, as `T`.
+    fn decode_all<T>(encoding: WireEncoding, wire: &[u8], chunk: usize) -> Vec<T>
+    where
+        T: DeserializeOwned + Serialize,
+    {
+        let mut c = codec(encoding, DEFAULT_MAX_LINE_BYTES);
+        let mut buf = BytesMut::new();
+        let mut out = Vec::new();
+        for piece in wire.chunks(chunk) {
+            buf.extend_from_slice(piece);
+            while let Some(frame) = c.decode(&mut buf).unwrap() {
+                out.push(decode_message(&frame.unwrap(), true).unwrap());
+            }
+        }
+        assert!(c.decode_eof(&mut buf).unwrap().is_none());
+        out
+    }
+
+    /// Compared through `serde_json::Value`, since the message types carry no `PartialEq`.
+    fn assert_round_trip<T>(messages: &[T])
+    where
+        T: DeserializeOwned + Serialize,
+    {
+        let as_values = |ms: &[T]| -> Vec<serde_json::Value> {
+            ms.iter()
+                .map(|m| serde_json::to_value(m).unwrap())
+                .collect()
+        };
+        let expected = as_values(messages);
+
+        for encoding in ENCODINGS {
+            let mut c = codec(encoding, DEFAULT_MAX_LINE_BYTES);
+            let mut wire = BytesMut::new();
+            for m in messages {
+                c.encode(m, &mut wire).unwrap();
+            }
+            for chunk in [wire.len(), 1] {
+                let got = decode_all::<T>(encoding, &wire, chunk);
+                assert_eq!(as_values(&got), expected, "{encoding:?} chunk={chunk}");
+            }
+        }
+    }
+
+    #[test]
+    fn round_trips_propose() {
+        assert_round_trip(&[sample_propose(), sample_propose()]);
+    }
+
+    #[test]
+    fn round_trips_verdict() {
+        assert_round_trip(&[sample_verdict(), sample_verdict()]);
+    }
+
+    #[test]
+    fn round_trips_error_frame() {
+        assert_round_trip(&[sample_error()]);
+    }
+
+    /// Oversized and corrupt input come out as protocol errors, and the stream
+    /// still decodes the next message.
+    #[test]
+    fn recovers_from_bad_input() {
+        for encoding in ENCODINGS {
+            let mut c = codec(encoding, 64);
+            let mut buf = BytesMut::new();
+            let expected_bad = match encoding {
+                WireEncoding::Json => {
+                    buf.extend_from_slice(
+                        format!("{{\"id\":5,\"pad\":\"{}\"}}\n", "x".repeat(200)).as_bytes(),
+                    );
+                    buf.extend_from_slice(b"{not json\n");
+                    ProtocolErrorCode::MalformedJson
+                }
+                WireEncoding::Cbor => {
+                    buf.extend_from_slice(&200u32.to_be_bytes());
+                    buf.extend_from_slice(&[0u8; 200]);
+                    buf.extend_from_slice(&2u32.to_be_bytes());
+                    buf.extend_from_slice(&[0xff, 0xff]);
+                    ProtocolErrorCode::MalformedCbor
+                }
+            };
+            c.encode(serde_json::json!({"id": 6}), &mut buf).unwrap();
+
+            let too_long = c.decode(&mut buf).unwrap().unwrap().unwrap_err();
+            assert_eq!(
+                too_long.code,
+                ProtocolErrorCode::LineTooLong,
+                "{encoding:?}"
+            );
+            if encoding == WireEncoding::Json {
+                assert_eq!(too_long.id, Some(5));
+            }
+            let bad = c.decode(&mut buf).unwrap().unwrap().unwrap_err();
+            assert_eq!(bad.code, expected_bad, "{encoding:?}");
+            let next = c.decode(&mut buf).unwrap().unwrap().unwrap();
+            assert_eq!(next["id"], 6, "{encoding:?}");
+            assert!(c.decode(&mut buf).unwrap().is_none());
+        }
+    }
+
+    #[test]
+    fn json_last_line_without_newline_decodes_at_eof() {
+        let mut c = MessageCodec::default();
+        let mut buf = BytesMut::from(&b"\n  \n{\"id\":1}"[..]);
+        assert!(c.decode(&mut buf).unwrap().is_none());
+        let frame = c.decode_eof(&mut buf).unwrap().unwrap().unwrap();
+        assert_eq!(frame["id"], 1);
+    }
+
+    #[test]
+    fn cbor_frame_cut_off_at_eof_is_malformed() {
+        let mut c = codec(WireEncoding::Cbor, DEFAULT_MAX_LINE_BYTES);
+        let mut buf = BytesMut::new();
+        c.encode(sample_propose(), &mut buf).unwrap();
+        buf.truncate(buf.len() - 3);
+        assert!(c.decode(&mut buf).unwrap().is_none());
+        let err = c.decode_eof(&mut buf).unwrap().unwrap().unwrap_err();
+        assert_eq!(err.code, ProtocolErrorCode::MalformedCbor);
+    }
+
+    #[test]
+    fn strict_decode_rejects_unknown_fields() {
+        let mut frame = serde_json::to_value(sample_propose()).unwrap();
+        frame["surprise"] = serde_json::json!(true);
+        assert!(decode_message::<TemplatePropose>(&frame, false).is_ok());
+        let err = decode_message::<TemplatePropose>(&frame, true).unwrap_err();
+        assert_eq!(err.code, ProtocolErrorCode::UnknownField);
+        assert_eq!(err.id, Some(sample_propose().id));
+    }
+}
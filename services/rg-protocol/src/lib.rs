@@ -1,7 +1,28 @@
 use serde::{Deserialize, Serialize};
 
+pub mod client;
+pub mod codec;
+
+pub use client::{ClientConfig, ClientError, Reply, VerifierClient};
+pub use codec::{MessageCodec, WireEncoding, decode_message};
+
 pub const PROTOCOL_VERSION: u16 = 2;
 
+/// Protocol versions this build can speak, oldest first.
+pub const SUPPORTED_VERSIONS: &[u16] = &[PROTOCOL_VERSION];
+
+/// Default cap on one protocol line, newline excluded.
+pub const DEFAULT_MAX_LINE_BYTES: usize = 1024 * 1024;
+
+/// Consensus block weight limit (BIP141), in weight units.
+pub const MAX_BLOCK_WEIGHT: u64 = 4_000_000;
+
+/// 80-byte block header, all non-witness data.
+pub const BLOCK_HEADER_WEIGHT: u64 = 80 * 4;
+
+/// Weight Bitcoin Core holds back from getblocktemplate for the coinbase tx.
+pub const COINBASE_RESERVED_WEIGHT: u64 = 4_000;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TemplatePropose {
     pub version: u16,
@@ -18,11 +39,21 @@ pub struct TemplatePropose {
     pub total_fees: u64,
 
     /// Forward compatible fields. Older senders omit them.
+    /// Full block weight in WU (header + coinbase reservation + transactions).
     #[serde(default)]
     pub observed_weight: Option<u64>,
 
     #[serde(default)]
     pub created_at_unix_ms: Option<u64>,
+
+    /// Sum of non-coinbase transaction vsizes (vB). Feerate denominator.
+    #[serde(default)]
+    pub total_vsize: Option<u64>,
+
+    /// Set when the sender's backend reported coinbase_value=0 and the sender
+    /// filled in a subsidy + fees fallback instead.
+    #[serde(default)]
+    pub coinbase_value_substituted: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -43,6 +74,30 @@ pub struct TemplateVerdict {
     /// Useful for “traceable rejects”: what policy decision was applied.
     #[serde(default)]
     pub policy_context: Option<PolicyContext>,
+
+    /// Every failed rule, primary first. `reason_code` mirrors `violations[0]`.
+    #[serde(default)]
+    pub violations: Vec<Violation>,
+
+    /// Failed rules the verifier runs in advisory mode; they never reject.
+    #[serde(default)]
+    pub warnings: Vec<Violation>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Violation {
+    pub reason_code: VerdictReason,
+
+    #[serde(default)]
+    pub reason_detail: Option<String>,
+
+    /// Policy limit the rule compared against, when it has a numeric one.
+    #[serde(default)]
+    pub threshold: Option<f64>,
+
+    /// Template value that failed the comparison.
+    #[serde(default)]
+    pub observed: Option<f64>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -57,21 +112,45 @@ pub enum VerdictReason {
     /// prev_hash length != required_prevhash_len
     PrevHashLenMismatch,
 
-    /// coinbase_value == 0 and reject_coinbase_zero enabled (non-empty templates)
+    /// prev_hash is not the current best block (chain-tip source configured)
+    StalePrevHash,
+
+    /// block_height != chain tip height + 1 (chain-tip source configured)
+    HeightMismatch,
+
+    /// created_at_unix_ms older than max_template_age_ms on arrival
+    TemplateTooOld,
+
+    /// created_at_unix_ms ahead of the verifier clock by more than max_clock_skew_ms
+    TemplateTimestampInFuture,
+
+    /// coinbase_value == 0 (or a sender-substituted fallback) and reject_coinbase_zero enabled (non-empty templates)
     CoinbaseValueZeroRejected,
 
+    /// coinbase_value > block subsidy + total_fees
+    CoinbaseValueOverclaim,
+
+    /// coinbase_value < block subsidy + total_fees - coinbase.underclaim_tolerance_sats
+    CoinbaseValueUnderclaim,
+
     /// tx_count == 0 and reject_empty_templates enabled
     EmptyTemplateRejected,
 
     /// tx_count > max_tx_count
     TxCountExceeded,
 
+    /// observed_weight > safety.max_weight_ratio * MAX_BLOCK_WEIGHT
+    WeightLimitExceeded,
+
     /// total_fees < min_total_fees
     TotalFeesBelowMinimum,
 
     /// (total_fees / tx_count) < effective min avg fee
     AvgFeeBelowMinimum,
 
+    /// (total_fees / total_vsize) < effective min feerate (sat/vB)
+    FeerateBelowMinimum,
+
     /// policy file could not be parsed/validated
     PolicyLoadError,
 
@@ -80,6 +159,36 @@ pub enum VerdictReason {
 
     /// unexpected internal failure
     InternalError,
+
+    /// failure of a verifier-specific rule; serialized as its own code string
+    #[serde(untagged)]
+    Custom(String),
+}
+
+impl VerdictReason {
+    /// Wire codes of every variant except `Custom`. A `Custom` code equal to one
+    /// of these would be read back as that variant.
+    pub const BUILTIN_CODES: &'static [&'static str] = &[
+        "protocol_version_mismatch",
+        "invalid_prev_hash",
+        "prev_hash_len_mismatch",
+        "stale_prev_hash",
+        "height_mismatch",
+        "template_too_old",
+        "template_timestamp_in_future",
+        "coinbase_value_zero_rejected",
+        "coinbase_value_overclaim",
+        "coinbase_value_underclaim",
+        "empty_template_rejected",
+        "tx_count_exceeded",
+        "weight_limit_exceeded",
+        "total_fees_below_minimum",
+        "avg_fee_below_minimum",
+        "feerate_below_minimum",
+        "policy_load_error",
+        "mempool_backend_unavailable",
+        "internal_error",
+    ];
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
@@ -98,4 +207,386 @@ pub struct PolicyContext {
 
     #[serde(default)]
     pub unknown_mempool_as_high: Option<bool>,
+
+    #[serde(default)]
+    pub max_weight_used: Option<u64>,
+
+    /// Template feerate in sat/vB, when the sender reported total_vsize.
+    #[serde(default)]
+    pub feerate_sat_vb: Option<f64>,
+
+    #[serde(default)]
+    pub min_feerate_used: Option<f64>,
+
+    /// When the verifier last switched `fee_tier` (sticky tier selection).
+    #[serde(default)]
+    pub tier_changed_at_unix_ms: Option<u64>,
+
+    /// Mempool measurement used for tier selection ("tx_count", "vbytes", ...).
+    #[serde(default)]
+    pub tier_metric: Option<String>,
+
+    /// Reading of `tier_metric` at verdict time; None when the mempool was unknown.
+    #[serde(default)]
+    pub tier_metric_value: Option<f64>,
+
+    /// Chain tip the template was checked against; None when no tip was known.
+    #[serde(default)]
+    pub chain_tip_hash: Option<String>,
+    #[serde(default)]
+    pub chain_tip_height: Option<u32>,
+
+    /// Verifier policy version that produced the verdict (see `/policy/history`).
+    #[serde(default)]
+    pub policy_version: Option<u64>,
+
+    /// Scheduled overlay (`[[policy.overlays]]`) in effect, if any.
+    #[serde(default)]
+    pub overlay: Option<String>,
+}
+
+/// Optional protocol features a peer can state in its `Hello`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Capability {
+    /// `TemplateVerdict.violations` lists every failed rule.
+    Violations,
+
+    /// `TemplateVerdict.warnings` lists advisory (warn mode) failures.
+    Warnings,
+
+    /// `TemplateVerdict.policy_context` is filled in.
+    PolicyContext,
+
+    /// Informational: the sender fills `TemplatePropose.total_vsize`. Feerate
+    /// floors apply to any template that carries it, negotiated or not.
+    Feerate,
+
+    /// Reserved for verifier-signed verdicts; no build offers it yet.
+    SignedVerdicts,
+
+    /// After the handshake both directions use length-prefixed CBOR frames
+    /// instead of JSON lines (see `WireEncoding`).
+    Cbor,
+
+    /// A capability this build does not know; never part of the settled set.
+    #[serde(untagged)]
+    Other(String),
+}
+
+impl Capability {
+    /// Wire name, as in a `Hello`.
+    pub fn as_str(&self) -> &str {
+        match self {
+            Capability::Violations => "violations",
+            Capability::Warnings => "warnings",
+            Capability::PolicyContext => "policy_context",
+            Capability::Feerate => "feerate",
+            Capability::SignedVerdicts => "signed_verdicts",
+            Capability::Cbor => "cbor",
+            Capability::Other(name) => name,
+        }
+    }
+}
+
+fn capability_list<'a>(caps: impl IntoIterator<Item = &'a Capability>) -> String {
+    caps.into_iter()
+        .map(Capability::as_str)
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// First line a connecting peer sends.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Hello {
+    /// Protocol versions the sender speaks.
+    pub versions: Vec<u16>,
+
+    #[serde(default)]
+    pub capabilities: Vec<Capability>,
+
+    /// Capabilities the sender cannot work without; the handshake fails if the
+    /// other side lacks one.
+    #[serde(default)]
+    pub required: Vec<Capability>,
+
+    /// Free-form peer name for logs, e.g. "template-manager/0.2.0".
+    #[serde(default)]
+    pub agent: String,
+}
+
+/// Reply to `Hello`. On success `version` is set and `capabilities` is the
+/// settled common set; otherwise `error` says why and the connection closes.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HelloAck {
+    #[serde(default)]
+    pub version: Option<u16>,
+
+    #[serde(default)]
+    pub capabilities: Vec<Capability>,
+
+    #[serde(default)]
+    pub agent: String,
+
+    #[serde(default)]
+    pub error: Option<String>,
+}
+
+/// Connection handshake frames, sent as `{"hello": {...}}` / `{"hello_ack": {...}}`
+/// lines so they cannot be mistaken for a `TemplatePropose`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HandshakeMessage {
+    Hello(Hello),
+    HelloAck(HelloAck),
+}
+
+/// What both sides settled on.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Negotiated {
+    pub version: u16,
+    pub capabilities: Vec<Capability>,
+}
+
+impl Negotiated {
+    /// Assumed for peers that skip the handshake: the current version with
+    /// every feature this build has always sent. Such peers only speak JSON.
+    pub fn legacy(capabilities: &[Capability]) -> Self {
+        Self {
+            version: PROTOCOL_VERSION,
+            capabilities: capabilities
+                .iter()
+                .filter(|c| **c != Capability::Cbor)
+                .cloned()
+                .collect(),
+        }
+    }
+
+    pub fn has(&self, capability: &Capability) -> bool {
+        self.capabilities.contains(capability)
+    }
+}
+
+impl Hello {
+    pub fn new(agent: impl Into<String>, capabilities: &[Capability]) -> Self {
+        Self {
+            versions: SUPPORTED_VERSIONS.to_vec(),
+            capabilities: capabilities.to_vec(),
+            required: Vec::new(),
+            agent: agent.into(),
+        }
+    }
+
+    /// Server side: settle on the highest common version and the shared
+    /// capabilities. `our_required` must be offered by the peer.
+    pub fn negotiate(
+        &self,
+        our_capabilities: &[Capability],
+        our_required: &[Capability],
+    ) -> Result<Negotiated, String> {
+        let version = self
+            .versions
+            .iter()
+            .filter(|v| SUPPORTED_VERSIONS.contains(v))
+            .max()
+            .copied()
+            .ok_or_else(|| {
+                format!(
+                    "no common protocol version: peer supports {:?}, we support {:?}",
+                    self.versions, SUPPORTED_VERSIONS
+                )
+            })?;
+
+        let missing = |wanted: &[Capability], offered: &[Capability]| -> Vec<Capability> {
+            wanted
+                .iter()
+                .filter(|c| !offered.contains(c))
+                .cloned()
+                .collect()
+        };
+        let ours_missing = missing(&self.required, our_capabilities);
+        if !ours_missing.is_empty() {
+            return Err(format!(
+                "peer requires capabilities we do not support: {}",
+                capability_list(&ours_missing)
+            ));
+        }
+        let theirs_missing = missing(our_required, &self.capabilities);
+        if !theirs_missing.is_empty() {
+            return Err(format!(
+                "peer lacks required capabilities: {}",
+                capability_list(&theirs_missing)
+            ));
+        }
+
+        let capabilities = self
+            .capabilities
+            .iter()
+            .filter(|c| !matches!(c, Capability::Other(_)) && our_capabilities.contains(c))
+            .cloned()
+            .collect();
+
+        Ok(Negotiated {
+            version,
+            capabilities,
+        })
+    }
+
+    /// Client side: check the server's reply against what we offered.
+    pub fn accept(&self, ack: &HelloAck) -> Result<Negotiated, String> {
+        if let Some(e) = &ack.error {
+            return Err(format!("peer {} rejected handshake: {}", ack.agent, e));
+        }
+        let version = match ack.version {
+            Some(v) if self.versions.contains(&v) => v,
+            Some(v) => {
+                return Err(format!(
+                    "peer {} picked protocol version {} we did not offer ({:?})",
+                    ack.agent, v, self.versions
+                ));
+            }
+            None => return Err(format!("peer {} sent no protocol version", ack.agent)),
+        };
+
+        let missing: Vec<&Capability> = self
+            .required
+            .iter()
+            .filter(|c| !ack.capabilities.contains(c))
+            .collect();
+        if !missing.is_empty() {
+            return Err(format!(
+                "peer {} lacks required capabilities: {}",
+                ack.agent,
+                capability_list(missing)
+            ));
+        }
+
+        Ok(Negotiated {
+            version,
+            capabilities: ack
+                .capabilities
+                .iter()
+                .filter(|c| self.capabilities.contains(c))
+                .cloned()
+                .collect(),
+        })
+    }
+}
+
+impl HelloAck {
+    pub fn accepted(agent: impl Into<String>, negotiated: &Negotiated) -> Self {
+        Self {
+            version: Some(negotiated.version),
+            capabilities: negotiated.capabilities.clone(),
+            agent: agent.into(),
+            error: None,
+        }
+    }
+
+    pub fn rejected(agent: impl Into<String>, error: impl Into<String>) -> Self {
+        Self {
+            version: None,
+            capabilities: Vec::new(),
+            agent: agent.into(),
+            error: Some(error.into()),
+        }
+    }
+}
+
+/// Why a receiver could not use a line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProtocolErrorCode {
+    /// Not valid JSON (or not UTF-8).
+    MalformedJson,
+
+    /// A binary frame that is not valid CBOR, or was cut off.
+    MalformedCbor,
+
+    /// Valid JSON, but not the expected message (missing or mistyped fields).
+    InvalidMessage,
+
+    /// Fields the receiver does not know, rejected in strict mode.
+    UnknownField,
+
+    /// Longer than the receiver's maximum line (or frame) length; it was discarded.
+    LineTooLong,
+}
+
+impl ProtocolErrorCode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ProtocolErrorCode::MalformedJson => "malformed_json",
+            ProtocolErrorCode::MalformedCbor => "malformed_cbor",
+            ProtocolErrorCode::InvalidMessage => "invalid_message",
+            ProtocolErrorCode::UnknownField => "unknown_field",
+            ProtocolErrorCode::LineTooLong => "line_too_long",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProtocolError {
+    /// `id` of the offending request, when it could be recovered.
+    #[serde(default)]
+    pub id: Option<u64>,
+
+    pub code: ProtocolErrorCode,
+
+    #[serde(default)]
+    pub detail: String,
+}
+
+impl std::fmt::Display for ProtocolError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.id {
+            Some(id) => write!(f, "{} (id={}): {}", self.code.as_str(), id, self.detail),
+            None => write!(f, "{}: {}", self.code.as_str(), self.detail),
+        }
+    }
+}
+
+/// Sent as `{"error": {...}}` in place of the reply to a line the receiver
+/// could not use. The connection stays open.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ErrorFrame {
+    pub error: ProtocolError,
+}
+
+/// Best-effort `"id": <number>` lookup in a line that may be truncated or
+/// not valid JSON.
+pub fn recover_request_id(line: &[u8]) -> Option<u64> {
+    const KEY: &[u8] = b"\"id\"";
+    let start = line.windows(KEY.len()).position(|w| w == KEY)? + KEY.len();
+
+    let rest = &line[start..];
+    let rest = &rest[rest.iter().position(|b| !b.is_ascii_whitespace())?..];
+    let rest = rest.strip_prefix(b":")?;
+    let rest = &rest[rest.iter().position(|b| !b.is_ascii_whitespace())?..];
+
+    let digits = rest.iter().take_while(|b| b.is_ascii_digit()).count();
+    std::str::from_utf8(&rest[..digits]).ok()?.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builtin_codes_decode_as_their_own_variant() {
+        for code in VerdictReason::BUILTIN_CODES {
+            let reason: VerdictReason = serde_json::from_value(serde_json::json!(code)).unwrap();
+            assert!(
+                !matches!(reason, VerdictReason::Custom(_)),
+                "{code} decoded as Custom"
+            );
+            assert_eq!(serde_json::to_value(&reason).unwrap(), *code);
+        }
+    }
+
+    #[test]
+    fn other_codes_decode_as_custom() {
+        let reason: VerdictReason = serde_json::from_value(serde_json::json!("no_dust")).unwrap();
+        assert!(matches!(reason, VerdictReason::Custom(ref c) if c == "no_dust"));
+    }
 }
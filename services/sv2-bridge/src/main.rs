@@ -4,11 +4,16 @@ use std::{
 };
 
 use anyhow::Result;
-use tokio::io::AsyncWriteExt;
+use futures_util::{SinkExt, StreamExt};
+use serde::Deserialize;
 use tokio::net::{TcpListener, TcpStream};
-use tokio::time::sleep;
+use tokio::time::{sleep, timeout};
+use tokio_util::codec::Framed;
 
-use rg_protocol::{PROTOCOL_VERSION, TemplatePropose};
+use rg_protocol::{
+    BLOCK_HEADER_WEIGHT, COINBASE_RESERVED_WEIGHT, Capability, HandshakeMessage, HelloAck,
+    MessageCodec, PROTOCOL_VERSION, TemplatePropose, WireEncoding,
+};
 
 #[derive(Clone)]
 struct BridgeConfig {
@@ -18,6 +23,9 @@ struct BridgeConfig {
     tx_count: u32,
     total_fees: u64,
 
+    // Synthetic per-transaction weight (WU) used to derive observed_weight.
+    tx_weight: u64,
+
     // Optional override. If set, used as the block subsidy (sats), independent of height.
     // Coinbase value will be subsidy + total_fees.
     subsidy_override_sats: Option<u64>,
@@ -48,6 +56,12 @@ impl BridgeConfig {
             .and_then(|s| s.parse().ok())
             .unwrap_or(100); // low on purpose so strict policy rejects
 
+        // ~141 vB, a typical 1-in/2-out P2WPKH spend.
+        let tx_weight = env::var("VELDRA_BRIDGE_TX_WEIGHT")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(561);
+
         // If you want “current mainnet-era” demo behavior, set:
         //   VELDRA_BRIDGE_SUBSIDY_SATS=312500000  (3.125 BTC)
         // Otherwise, we compute subsidy by height (regtest at height=500 -> 50 BTC).
@@ -61,6 +75,7 @@ impl BridgeConfig {
             start_height,
             tx_count,
             total_fees,
+            tx_weight,
             subsidy_override_sats,
         }
     }
@@ -71,12 +86,13 @@ async fn main() -> Result<()> {
     let cfg = BridgeConfig::from_env();
 
     println!(
-        "sv2-bridge listening on {} (interval={}s, start_height={}, tx_count={}, total_fees={}, subsidy_override_sats={})",
+        "sv2-bridge listening on {} (interval={}s, start_height={}, tx_count={}, total_fees={}, tx_weight={}, subsidy_override_sats={})",
         cfg.listen_addr,
         cfg.interval_secs,
         cfg.start_height,
         cfg.tx_count,
         cfg.total_fees,
+        cfg.tx_weight,
         cfg.subsidy_override_sats
             .map(|v| v.to_string())
             .unwrap_or_else(|| "none(height-derived)".to_string()),
@@ -95,7 +111,54 @@ async fn main() -> Result<()> {
     }
 }
 
-async fn handle_client(mut stream: TcpStream, cfg: BridgeConfig) -> Result<()> {
+/// The bridge only produces proposals; `total_vsize` is its one optional field.
+const BRIDGE_CAPABILITIES: &[Capability] = &[Capability::Feerate, Capability::Cbor];
+
+/// Answer a client's `Hello`. Clients that send nothing within the timeout
+/// predate the handshake and just get the template stream.
+async fn handshake(framed: &mut Framed<TcpStream, MessageCodec>) -> Result<()> {
+    let agent = format!("sv2-bridge/{}", env!("CARGO_PKG_VERSION"));
+
+    let frame = match timeout(Duration::from_secs(2), framed.next()).await {
+        Err(_) => return Ok(()),
+        Ok(None) => anyhow::bail!("client closed connection before handshake"),
+        Ok(Some(Ok(Ok(frame)))) => frame,
+        Ok(Some(Ok(Err(_)))) => return Ok(()),
+        Ok(Some(Err(e))) => return Err(e.into()),
+    };
+
+    let Ok(HandshakeMessage::Hello(hello)) = HandshakeMessage::deserialize(&frame) else {
+        return Ok(());
+    };
+
+    let (ack, result) = match hello.negotiate(BRIDGE_CAPABILITIES, &[]) {
+        Ok(n) => (HelloAck::accepted(agent, &n), Ok(n)),
+        Err(e) => (HelloAck::rejected(agent, e.clone()), Err(e)),
+    };
+    framed.send(HandshakeMessage::HelloAck(ack)).await?;
+
+    match result {
+        Ok(n) => {
+            let encoding = WireEncoding::negotiated(&n);
+            framed.codec_mut().set_encoding(encoding);
+            println!(
+                "[{}] handshake agent={} version={} capabilities={:?} encoding={}",
+                now_secs(),
+                hello.agent,
+                n.version,
+                n.capabilities,
+                encoding.as_str()
+            );
+            Ok(())
+        }
+        Err(e) => anyhow::bail!("handshake rejected agent={}: {}", hello.agent, e),
+    }
+}
+
+async fn handle_client(stream: TcpStream, cfg: BridgeConfig) -> Result<()> {
+    let mut framed = Framed::new(stream, MessageCodec::default());
+    handshake(&mut framed).await?;
+
     let mut id: u64 = 1;
     let mut height: u32 = cfg.start_height;
 
@@ -116,6 +179,11 @@ async fn handle_client(mut stream: TcpStream, cfg: BridgeConfig) -> Result<()> {
         // In Bitcoin Core getblocktemplate, coinbasevalue includes subsidy + fees.
         let coinbase_value: u64 = subsidy_sats.saturating_add(cfg.total_fees);
 
+        // Header + coinbase reservation + synthetic tx body, mirroring template-manager.
+        let observed_weight: u64 = (BLOCK_HEADER_WEIGHT + COINBASE_RESERVED_WEIGHT)
+            .saturating_add((cfg.tx_count as u64).saturating_mul(cfg.tx_weight));
+        let total_vsize: u64 = (cfg.tx_count as u64).saturating_mul(cfg.tx_weight.div_ceil(4));
+
         let tpl = TemplatePropose {
             version: PROTOCOL_VERSION,
             id,
@@ -126,24 +194,24 @@ async fn handle_client(mut stream: TcpStream, cfg: BridgeConfig) -> Result<()> {
             total_fees: cfg.total_fees,
 
             // v0.2.0 forward-compatible fields
-            observed_weight: None,
+            observed_weight: Some(observed_weight),
             created_at_unix_ms: Some(now_ms),
+            total_vsize: Some(total_vsize),
+            coinbase_value_substituted: false,
         };
 
-        let json = serde_json::to_string(&tpl)?;
-        stream.write_all(json.as_bytes()).await?;
-        stream.write_all(b"\n").await?;
-        stream.flush().await?;
+        framed.send(&tpl).await?;
 
         println!(
-            "[{}] sent template id={} height={} subsidy_sats={} total_fees={} coinbase_value={} tx_count={}",
+            "[{}] sent template id={} height={} subsidy_sats={} total_fees={} coinbase_value={} tx_count={} weight={}",
             now_secs(),
             id,
             height,
             subsidy_sats,
             cfg.total_fees,
             coinbase_value,
-            cfg.tx_count
+            cfg.tx_count,
+            observed_weight
         );
 
         id += 1;
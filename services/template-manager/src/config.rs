@@ -1,4 +1,5 @@
 use anyhow::{Context, Result, bail};
+use rg_protocol::WireEncoding;
 use serde::Deserialize;
 use std::fs;
 use std::path::Path;
@@ -17,6 +18,12 @@ struct ManagerTable {
     verifier_tcp_addr: Option<String>,
     http_listen_addr: Option<String>,
 
+    // Verifier HTTP base, e.g. "http://127.0.0.1:8080"; enables chain tip pushes
+    verifier_http_url: Option<String>,
+
+    // "json" (default) or "cbor"; offered in the handshake, used if the peer agrees
+    wire_encoding: Option<String>,
+
     // Flat bitcoind (your screenshot manager.toml)
     rpc_url: Option<String>,
     rpc_user: Option<String>,
@@ -51,6 +58,8 @@ pub struct TemplateManagerConfig {
 
     pub verifier_tcp_addr: Option<String>,
     pub http_listen_addr: Option<String>,
+    pub verifier_http_url: Option<String>,
+    pub wire_encoding: WireEncoding,
 
     pub rpc_url: Option<String>,
     pub rpc_user: Option<String>,
@@ -113,12 +122,19 @@ impl TemplateManagerConfig {
             stratum_auth = s.auth;
         }
 
+        let wire_encoding = match mgr.wire_encoding {
+            Some(s) => s.parse().map_err(anyhow::Error::msg)?,
+            None => WireEncoding::default(),
+        };
+
         Ok(TemplateManagerConfig {
             backend: mgr.backend,
             poll_interval_secs: mgr.poll_interval_secs,
 
             verifier_tcp_addr: mgr.verifier_tcp_addr,
             http_listen_addr: mgr.http_listen_addr,
+            verifier_http_url: mgr.verifier_http_url,
+            wire_encoding,
 
             rpc_url,
             rpc_user,
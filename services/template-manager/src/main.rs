@@ -7,22 +7,28 @@ use std::{
 };
 
 use anyhow::{Context, Result};
-use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use futures_util::{SinkExt, StreamExt};
 use tokio::net::{TcpListener, TcpStream};
 use tokio::sync::{RwLock, mpsc};
-use tokio::time::{Duration, sleep, timeout};
+use tokio::time::{Duration, sleep};
+use tokio_util::codec::Framed;
 
 use axum::{Extension, Json, Router, routing::get};
 use bitcoincore_rpc::json::{
     GetBlockTemplateCapabilities, GetBlockTemplateModes, GetBlockTemplateRules,
 };
 use bitcoincore_rpc::{Auth, Client, RpcApi};
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 
-use rg_protocol::{PROTOCOL_VERSION, TemplatePropose, TemplateVerdict};
+use rg_protocol::{
+    BLOCK_HEADER_WEIGHT, COINBASE_RESERVED_WEIGHT, Capability, ClientConfig, HandshakeMessage,
+    Hello, MessageCodec, PROTOCOL_VERSION, TemplatePropose, WireEncoding, decode_message,
+};
 
 mod config;
+mod verifier_link;
 use config::TemplateManagerConfig;
+use verifier_link::VerifierLink;
 
 use async_trait::async_trait;
 
@@ -62,6 +68,15 @@ fn block_subsidy_sats(height: u32) -> u64 {
     (50u64 * 100_000_000u64) >> halvings
 }
 
+fn block_weight_from_template_txs(tx_weights: impl Iterator<Item = u64>) -> u64 {
+    BLOCK_HEADER_WEIGHT + COINBASE_RESERVED_WEIGHT + tx_weights.sum::<u64>()
+}
+
+/// BIP141 virtual size: weight / 4, rounded up.
+fn vsize_from_weight(weight: u64) -> u64 {
+    weight.div_ceil(4)
+}
+
 fn stable_template_id(fp: &TemplateFingerprint) -> u64 {
     let mut h = DefaultHasher::new();
     fp.height.hash(&mut h);
@@ -88,19 +103,70 @@ fn build_bitcoind_client(cfg: &TemplateManagerConfig) -> anyhow::Result<Arc<Clie
     Ok(Arc::new(client))
 }
 
+/// Pushes bitcoind's best block to the verifier's `POST /chain/tip`,
+/// so it can reject templates built on a stale tip.
+struct TipPusher {
+    url: String,
+    http: reqwest::Client,
+}
+
+impl TipPusher {
+    fn new(verifier_http_url: &str) -> Result<Self> {
+        let http = reqwest::Client::builder()
+            .timeout(Duration::from_secs(1))
+            .build()
+            .context("failed to build HTTP client for chain tip pushes")?;
+        Ok(Self {
+            url: format!("{}/chain/tip", verifier_http_url.trim_end_matches('/')),
+            http,
+        })
+    }
+
+    async fn push(&self, client: Arc<Client>) {
+        let info = match tokio::task::spawn_blocking(move || client.get_blockchain_info()).await {
+            Ok(Ok(info)) => info,
+            Ok(Err(e)) => {
+                eprintln!("[manager] get_blockchain_info failed: {e:?}");
+                return;
+            }
+            Err(join_err) => {
+                eprintln!("[manager] get_blockchain_info spawn_blocking join error: {join_err:?}");
+                return;
+            }
+        };
+
+        let body = serde_json::json!({
+            "hash": info.best_block_hash.to_string(),
+            "height": info.blocks,
+        });
+
+        match self.http.post(&self.url).json(&body).send().await {
+            Ok(r) if r.status().is_success() => {}
+            Ok(r) => eprintln!(
+                "[manager] chain tip push to {} got {}",
+                self.url,
+                r.status()
+            ),
+            Err(e) => eprintln!("[manager] chain tip push to {} failed: {e:?}", self.url),
+        }
+    }
+}
+
 /// Bitcoind-backed template source using getblocktemplate.
 struct BitcoindTemplateSource {
     client: Arc<Client>,
     last_fp: Option<TemplateFingerprint>,
     had_rpc_error: bool,
+    tip_pusher: Option<TipPusher>,
 }
 
 impl BitcoindTemplateSource {
-    fn new(client: Arc<Client>) -> Self {
+    fn new(client: Arc<Client>, tip_pusher: Option<TipPusher>) -> Self {
         Self {
             client,
             last_fp: None,
             had_rpc_error: false,
+            tip_pusher,
         }
     }
 }
@@ -166,14 +232,28 @@ impl TemplateSource for BitcoindTemplateSource {
             None => return Ok(None),
         };
 
+        // After getblocktemplate, so a block found in between makes this template
+        // (correctly) stale rather than the tip. Every poll, to keep the tip fresh.
+        if let Some(ref pusher) = self.tip_pusher {
+            pusher.push(self.client.clone()).await;
+        }
+
         let block_height = tpl.height as u32;
         let prev_hash = tpl.previous_block_hash.to_string();
 
         let tx_count = tpl.transactions.len() as u32;
         let total_fees: u64 = tpl.transactions.iter().map(|tx| tx.fee.to_sat()).sum();
+        let observed_weight =
+            block_weight_from_template_txs(tpl.transactions.iter().map(|tx| tx.weight as u64));
+        let total_vsize: u64 = tpl
+            .transactions
+            .iter()
+            .map(|tx| vsize_from_weight(tx.weight as u64))
+            .sum();
 
         let coinbase_raw: u64 = tpl.coinbase_value.to_sat();
-        let coinbase_value: u64 = if coinbase_raw == 0 {
+        let coinbase_value_substituted = coinbase_raw == 0;
+        let coinbase_value: u64 = if coinbase_value_substituted {
             let fallback = block_subsidy_sats(block_height) + total_fees;
             eprintln!(
                 "[manager] WARNING coinbase_value=0 from getblocktemplate at height={} tx_count={} total_fees={}; using fallback={}",
@@ -214,12 +294,34 @@ impl TemplateSource for BitcoindTemplateSource {
             coinbase_value,
             tx_count,
             total_fees,
-            observed_weight: None,
+            observed_weight: Some(observed_weight),
             created_at_unix_ms: Some(now_unix_ms()),
+            total_vsize: Some(total_vsize),
+            coinbase_value_substituted,
         }))
     }
 }
 
+/// Features the manager offers to the verifier and the bridge.
+const MANAGER_CAPABILITIES: &[Capability] = &[
+    Capability::Violations,
+    Capability::Warnings,
+    Capability::PolicyContext,
+    Capability::Feerate,
+];
+
+/// CBOR is only offered when configured, so JSON stays the default.
+fn manager_hello(encoding: WireEncoding) -> Hello {
+    let mut capabilities = MANAGER_CAPABILITIES.to_vec();
+    if encoding == WireEncoding::Cbor {
+        capabilities.push(Capability::Cbor);
+    }
+    Hello::new(
+        format!("template-manager/{}", env!("CARGO_PKG_VERSION")),
+        &capabilities,
+    )
+}
+
 /// Stratum-backed template source.
 /// Expects a local bridge that sends TemplatePropose as newline-delimited JSON.
 struct StratumTemplateSource {
@@ -233,6 +335,7 @@ impl StratumTemplateSource {
             .clone()
             .unwrap_or_else(|| "127.0.0.1:3333".to_string());
         let auth = cfg.stratum_auth.clone();
+        let encoding = cfg.wire_encoding;
 
         println!(
             "StratumTemplateSource connecting to Stratum V2 bridge at {} auth_set={}",
@@ -247,30 +350,59 @@ impl StratumTemplateSource {
                 match TcpStream::connect(&addr).await {
                     Ok(stream) => {
                         println!("Connected to Stratum V2 bridge at {}", addr);
-                        let mut reader = BufReader::new(stream);
-                        let mut line = String::new();
+                        let mut framed = Framed::new(stream, MessageCodec::default());
+                        let hello = manager_hello(encoding);
+                        if let Err(e) = framed.send(HandshakeMessage::Hello(hello.clone())).await {
+                            eprintln!("failed to send Hello to Stratum V2 bridge: {e:?}");
+                            sleep(Duration::from_secs(3)).await;
+                            continue;
+                        }
+                        // A bridge that predates the handshake starts with a template.
+                        let mut handshake_pending = true;
 
                         loop {
-                            line.clear();
-                            let n = match reader.read_line(&mut line).await {
-                                Ok(n) => n,
-                                Err(e) => {
+                            let frame = match framed.next().await {
+                                Some(Ok(Ok(frame))) => frame,
+                                Some(Ok(Err(e))) => {
+                                    eprintln!("unreadable line from Stratum V2 bridge: {e}");
+                                    continue;
+                                }
+                                Some(Err(e)) => {
                                     eprintln!("error reading from Stratum V2 bridge: {e:?}");
                                     break;
                                 }
+                                None => {
+                                    println!("Stratum V2 bridge closed connection");
+                                    break;
+                                }
                             };
 
-                            if n == 0 {
-                                println!("Stratum V2 bridge closed connection");
-                                break;
-                            }
-
-                            let s = line.trim();
-                            if s.is_empty() {
-                                continue;
+                            if handshake_pending {
+                                handshake_pending = false;
+                                if let Ok(HandshakeMessage::HelloAck(ack)) =
+                                    HandshakeMessage::deserialize(&frame)
+                                {
+                                    match hello.accept(&ack) {
+                                        Ok(n) => {
+                                            let encoding = WireEncoding::negotiated(&n);
+                                            framed.codec_mut().set_encoding(encoding);
+                                            println!(
+                                                "Stratum V2 bridge handshake ok version={} capabilities={:?} encoding={}",
+                                                n.version,
+                                                n.capabilities,
+                                                encoding.as_str()
+                                            );
+                                            continue;
+                                        }
+                                        Err(e) => {
+                                            eprintln!("Stratum V2 bridge handshake failed: {e}");
+                                            break;
+                                        }
+                                    }
+                                }
                             }
 
-                            match serde_json::from_str::<TemplatePropose>(s) {
+                            match decode_message::<TemplatePropose>(&frame, false) {
                                 Ok(tpl) => {
                                     if tx.send(tpl).await.is_err() {
                                         eprintln!(
@@ -281,8 +413,8 @@ impl StratumTemplateSource {
                                 }
                                 Err(e) => {
                                     eprintln!(
-                                        "failed to parse TemplatePropose JSON from Stratum V2 bridge: {e:?} line={:?}",
-                                        s
+                                        "failed to parse TemplatePropose from Stratum V2 bridge: {e} frame={}",
+                                        frame
                                     );
                                 }
                             }
@@ -328,6 +460,7 @@ struct MempoolStats {
     bytes: u64,
     usage: u64,
     max: u64,
+    /// mempoolminfee in sat/kvB.
     min_relay_fee: u64,
     timestamp: u64,
 }
@@ -342,7 +475,15 @@ async fn main() -> Result<()> {
     let cfg_path =
         env::var("VELDRA_MANAGER_CONFIG").unwrap_or_else(|_| "config/manager.toml".to_string());
 
-    let cfg = TemplateManagerConfig::from_path(&cfg_path)?;
+    let mut cfg = TemplateManagerConfig::from_path(&cfg_path)?;
+    if let Ok(s) = env::var("VELDRA_WIRE_ENCODING")
+        && !s.trim().is_empty()
+    {
+        cfg.wire_encoding = s
+            .parse()
+            .map_err(anyhow::Error::msg)
+            .context("invalid VELDRA_WIRE_ENCODING")?;
+    }
     println!("Loaded manager config from {}: {:?}", cfg_path, cfg);
 
     let verifier_addr = env::var("VELDRA_VERIFIER_ADDR")
@@ -357,6 +498,15 @@ async fn main() -> Result<()> {
 
     let poll_secs: u64 = cfg.poll_interval_secs.unwrap_or(5).max(1);
 
+    let verifier_http_url = env::var("VELDRA_VERIFIER_HTTP_URL")
+        .ok()
+        .filter(|s| !s.trim().is_empty())
+        .or_else(|| {
+            cfg.verifier_http_url
+                .clone()
+                .filter(|s| !s.trim().is_empty())
+        });
+
     let http_addr = env::var("VELDRA_MANAGER_HTTP_ADDR")
         .ok()
         .filter(|s| !s.trim().is_empty())
@@ -368,8 +518,12 @@ async fn main() -> Result<()> {
         .unwrap_or_else(|| "127.0.0.1:8081".to_string());
 
     println!(
-        "Template manager backend={} polling every {}s, sending to verifier {}, HTTP at {}",
-        cfg.backend, poll_secs, verifier_addr, http_addr
+        "Template manager backend={} polling every {}s, sending to verifier {} (wire encoding {}), HTTP at {}",
+        cfg.backend,
+        poll_secs,
+        verifier_addr,
+        cfg.wire_encoding.as_str(),
+        http_addr
     );
 
     // ---- SINGLE-INSTANCE LOCK ----
@@ -389,8 +543,17 @@ async fn main() -> Result<()> {
         "bitcoind" => {
             let client: Arc<Client> = build_bitcoind_client(&cfg)?; // NOTE the `?`
 
+            let tip_pusher = match verifier_http_url {
+                Some(ref url) => {
+                    println!("Pushing chain tip to verifier at {}", url);
+                    Some(TipPusher::new(url)?)
+                }
+                None => None,
+            };
+
             (
-                Box::new(BitcoindTemplateSource::new(client.clone())) as Box<dyn TemplateSource>,
+                Box::new(BitcoindTemplateSource::new(client.clone(), tip_pusher))
+                    as Box<dyn TemplateSource>,
                 "bitcoind".to_string(),
                 Some(client),
             )
@@ -415,10 +578,16 @@ async fn main() -> Result<()> {
     // run HTTP server (if it dies, we stop)
     let http_task = tokio::spawn(async move { axum::serve(listener, app).await });
 
+    let link = VerifierLink::spawn(
+        verifier_addr,
+        manager_hello(cfg.wire_encoding),
+        ClientConfig::default(),
+    );
+
     // run manager loop (if it dies, we stop)
     let manager_task = tokio::spawn(run_manager_loop(
         source,
-        verifier_addr,
+        link,
         poll_secs,
         backend_name.clone(),
         template_log,
@@ -493,7 +662,7 @@ async fn fetch_mempool_info_with_retries(
 
 async fn run_manager_loop(
     mut source: Box<dyn TemplateSource>,
-    verifier_addr: String,
+    link: VerifierLink,
     poll_secs: u64,
     backend_name: String,
     template_log: TemplateLog,
@@ -502,15 +671,12 @@ async fn run_manager_loop(
 ) -> Result<()> {
     let mut mempool_had_rpc_error = false;
 
-    let connect_timeout = Duration::from_secs(2);
-    let verdict_timeout = Duration::from_secs(4);
-
     loop {
         // ---- template handling ----
         match source.next_template().await {
             Ok(Some(propose)) => {
                 println!(
-                    "New template backend={} id={} height={} prev_hash={} coinbase_value={} total_fees={} tx_count={}",
+                    "New template backend={} id={} height={} prev_hash={} coinbase_value={} total_fees={} tx_count={} weight={:?}",
                     backend_name,
                     propose.id,
                     propose.block_height,
@@ -518,28 +684,11 @@ async fn run_manager_loop(
                     propose.coinbase_value,
                     propose.total_fees,
                     propose.tx_count,
+                    propose.observed_weight,
                 );
 
-                match timeout(connect_timeout, TcpStream::connect(&verifier_addr)).await {
-                    Ok(Ok(stream)) => {
-                        match timeout(verdict_timeout, send_and_receive(stream, &propose)).await {
-                            Ok(Ok(())) => {}
-                            Ok(Err(e)) => eprintln!(
-                                "[manager] error sending template id={} to verifier {}: {e:?}",
-                                propose.id, verifier_addr
-                            ),
-                            Err(_) => eprintln!(
-                                "[manager] verifier timed out (send/recv) id={} addr={}",
-                                propose.id, verifier_addr
-                            ),
-                        }
-                    }
-                    Ok(Err(e)) => eprintln!(
-                        "[manager] failed to connect to verifier {}: {e:?}",
-                        verifier_addr
-                    ),
-                    Err(_) => eprintln!("[manager] connect timeout to verifier {}", verifier_addr),
-                }
+                // Verdicts are logged by the link as they come back.
+                link.submit(propose.clone()).await?;
 
                 // store for /templates
                 {
@@ -592,30 +741,6 @@ async fn run_manager_loop(
     }
 }
 
-async fn send_and_receive(mut stream: TcpStream, propose: &TemplatePropose) -> Result<()> {
-    let (reader, mut writer) = stream.split();
-    let mut reader = BufReader::new(reader);
-
-    let json = serde_json::to_string(propose)?;
-    timeout(Duration::from_secs(2), writer.write_all(json.as_bytes())).await??;
-    timeout(Duration::from_secs(2), writer.write_all(b"\n")).await??;
-    timeout(Duration::from_secs(2), writer.flush()).await??;
-
-    let mut line = String::new();
-    let bytes_read = timeout(Duration::from_secs(3), reader.read_line(&mut line)).await??;
-    if bytes_read == 0 {
-        anyhow::bail!("verifier closed connection without sending a verdict");
-    }
-
-    let verdict: TemplateVerdict = serde_json::from_str(line.trim())?;
-    println!(
-        "Received TemplateVerdict id={} accepted={} reason_code={:?} detail={:?}",
-        verdict.id, verdict.accepted, verdict.reason_code, verdict.reason_detail,
-    );
-
-    Ok(())
-}
-
 // HTTP handlers
 
 async fn health_check() -> &'static str {
@@ -0,0 +1,231 @@
+//! Long-lived, pipelined connection to the verifier.
+//!
+//! Templates go through a channel to one task that owns the connection. Up to
+//! `MAX_IN_FLIGHT` proposals are outstanding at once and verdicts are matched
+//! back by `id`. When the connection drops, unanswered proposals are queued
+//! again and resent after reconnecting, so a verifier restart loses nothing.
+
+use std::collections::VecDeque;
+
+use anyhow::Result;
+use tokio::sync::mpsc;
+use tokio::time::{Duration, Instant, sleep_until};
+
+use rg_protocol::{
+    ClientConfig, ClientError, Hello, Reply, TemplatePropose, TemplateVerdict, VerifierClient,
+};
+
+const MAX_IN_FLIGHT: usize = 32;
+/// Templates held while the verifier is unreachable; the oldest are dropped first.
+const MAX_QUEUED: usize = 256;
+const BACKOFF_MIN: Duration = Duration::from_millis(200);
+const BACKOFF_MAX: Duration = Duration::from_secs(10);
+
+#[derive(Clone)]
+pub struct VerifierLink {
+    tx: mpsc::Sender<TemplatePropose>,
+}
+
+impl VerifierLink {
+    pub fn spawn(addr: String, hello: Hello, config: ClientConfig) -> Self {
+        let (tx, rx) = mpsc::channel(64);
+        let task = LinkTask {
+            addr,
+            hello,
+            config,
+            rx,
+            queued: VecDeque::new(),
+            in_flight: VecDeque::new(),
+        };
+        tokio::spawn(task.run());
+        Self { tx }
+    }
+
+    /// Hand a template to the link. Returns once it is queued, not when the
+    /// verdict arrives.
+    pub async fn submit(&self, propose: TemplatePropose) -> Result<()> {
+        self.tx
+            .send(propose)
+            .await
+            .map_err(|_| anyhow::anyhow!("verifier link task stopped"))
+    }
+}
+
+struct LinkTask {
+    addr: String,
+    hello: Hello,
+    config: ClientConfig,
+    rx: mpsc::Receiver<TemplatePropose>,
+    /// Not sent on the current connection yet, oldest first.
+    queued: VecDeque<TemplatePropose>,
+    /// Sent and waiting for a verdict, in send order.
+    in_flight: VecDeque<TemplatePropose>,
+}
+
+impl LinkTask {
+    async fn run(mut self) {
+        let mut backoff = BACKOFF_MIN;
+
+        loop {
+            let mut client = match VerifierClient::connect(&self.addr, &self.hello, &self.config)
+                .await
+            {
+                Ok(c) => c,
+                Err(e) => {
+                    eprintln!(
+                        "[manager] verifier {} unavailable: {e}; retrying in {:?} ({} templates queued)",
+                        self.addr,
+                        backoff,
+                        self.queued.len()
+                    );
+                    if !self.wait_queueing(backoff).await {
+                        return;
+                    }
+                    backoff = (backoff * 2).min(BACKOFF_MAX);
+                    continue;
+                }
+            };
+            backoff = BACKOFF_MIN;
+            println!(
+                "[manager] connected to verifier {} agent={} version={} capabilities={:?} queued={}",
+                self.addr,
+                client.peer_agent(),
+                client.negotiated().version,
+                client.negotiated().capabilities,
+                self.queued.len()
+            );
+
+            match self.serve(&mut client).await {
+                Ok(()) => return,
+                Err(e) => {
+                    eprintln!(
+                        "[manager] verifier connection {} lost: {e}; {} templates unanswered",
+                        self.addr,
+                        self.in_flight.len()
+                    );
+                    // Unanswered proposals go back in front of newer ones.
+                    while let Some(p) = self.in_flight.pop_back() {
+                        self.queued.push_front(p);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Pipeline templates over one connection until it fails. Ok means the
+    /// manager loop is gone.
+    async fn serve(&mut self, client: &mut VerifierClient) -> Result<(), ClientError> {
+        // Last reply, or the moment the pipeline went from idle to busy.
+        let mut last_progress = Instant::now();
+
+        loop {
+            while self.in_flight.len() < MAX_IN_FLIGHT
+                && let Some(p) = self.queued.pop_front()
+            {
+                if let Err(e) = client.send(&p).await {
+                    self.queued.push_front(p);
+                    return Err(e);
+                }
+                if self.in_flight.is_empty() {
+                    last_progress = Instant::now();
+                }
+                self.in_flight.push_back(p);
+            }
+
+            // The verifier answers in order, so silence this long with work
+            // outstanding means it is stuck rather than busy.
+            let deadline = last_progress + self.config.request_timeout;
+            tokio::select! {
+                next = self.rx.recv() => match next {
+                    Some(p) => self.enqueue(p),
+                    None => return Ok(()),
+                },
+                reply = client.recv() => match reply {
+                    Ok(reply) => {
+                        last_progress = Instant::now();
+                        self.complete(reply);
+                    }
+                    Err(ClientError::Protocol(e)) => {
+                        eprintln!("[manager] unreadable reply from verifier {}: {e}", self.addr);
+                    }
+                    Err(e) => return Err(e),
+                },
+                _ = sleep_until(deadline), if !self.in_flight.is_empty() => {
+                    // Do not resend the template the verifier choked on.
+                    if let Some(p) = self.in_flight.pop_front() {
+                        eprintln!(
+                            "[manager] verifier timed out id={} addr={}, dropping it",
+                            p.id, self.addr
+                        );
+                    }
+                    return Err(ClientError::Timeout("waiting for verdict"));
+                }
+            }
+        }
+    }
+
+    fn complete(&mut self, reply: Reply) {
+        // Error frames without an id answer the oldest proposal, since replies come in order.
+        let pos = match reply.id() {
+            Some(id) => self.in_flight.iter().position(|p| p.id == id),
+            None if !self.in_flight.is_empty() => Some(0),
+            None => None,
+        };
+        let Some(propose) = pos.and_then(|i| self.in_flight.remove(i)) else {
+            eprintln!(
+                "[manager] reply for id={:?} matches no template in flight",
+                reply.id()
+            );
+            return;
+        };
+
+        match reply {
+            Reply::Verdict(v) => log_verdict(&v),
+            Reply::Error(e) => eprintln!(
+                "[manager] verifier could not read template id={}: {e}",
+                propose.id
+            ),
+        }
+    }
+
+    fn enqueue(&mut self, propose: TemplatePropose) {
+        if self.queued.len() >= MAX_QUEUED
+            && let Some(old) = self.queued.pop_front()
+        {
+            eprintln!(
+                "[manager] verifier queue full, dropping template id={} height={}",
+                old.id, old.block_height
+            );
+        }
+        self.queued.push_back(propose);
+    }
+
+    /// Sleep out a backoff while still taking templates, so the manager loop
+    /// never blocks on a down verifier. False when the manager loop is gone.
+    async fn wait_queueing(&mut self, delay: Duration) -> bool {
+        let until = Instant::now() + delay;
+        loop {
+            tokio::select! {
+                _ = sleep_until(until) => return true,
+                next = self.rx.recv() => match next {
+                    Some(p) => self.enqueue(p),
+                    None => return false,
+                },
+            }
+        }
+    }
+}
+
+fn log_verdict(verdict: &TemplateVerdict) {
+    println!(
+        "Received TemplateVerdict id={} accepted={} reason_code={:?} detail={:?}",
+        verdict.id, verdict.accepted, verdict.reason_code, verdict.reason_detail,
+    );
+    for w in &verdict.warnings {
+        println!(
+            "  policy warning (not enforced): {:?} {}",
+            w.reason_code,
+            w.reason_detail.as_deref().unwrap_or("")
+        );
+    }
+}